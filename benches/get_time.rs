@@ -0,0 +1,62 @@
+//! Measures the per-call overhead of reading the current time.
+//!
+//! On the web, this compares `PerformanceClock` against the `window().performance().now()` path
+//! the crate used before it cached the lookup, and on native it compares `MonotonicClock` against
+//! reading `Instant` directly. Run it with `cargo bench`, or build it for
+//! `wasm32-unknown-unknown` and run the output in a browser, in which case results are logged to
+//! the console.
+
+use std::hint::black_box;
+
+use wasm_stopwatch::time_source::*;
+use wasm_stopwatch::Stopwatch;
+
+const CALLS: u32 = 1_000_000;
+
+fn main() {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        match web_sys::window() {
+            Some(window) => measure("window().performance().now()", || {
+                black_box(window.performance().unwrap().now());
+            }),
+            None => report("window().performance().now(): skipped, there's no window"),
+        }
+        measure("PerformanceClock::now", || {
+            black_box(PerformanceClock.now());
+        });
+    }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    {
+        measure("Instant::now", || {
+            black_box(std::time::Instant::now());
+        });
+        measure("MonotonicClock::now", || {
+            black_box(MonotonicClock.now());
+        });
+    }
+}
+
+fn measure(name: &str, mut f: impl FnMut()) {
+    let timer = Stopwatch::new();
+    for _ in 0..CALLS {
+        f();
+    }
+    let nanos_per_call = timer.get_time() * 1e9 / CALLS as f64;
+    report(&format!("{}: {:.1} ns/call", name, nanos_per_call));
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn report(message: &str) {
+    println!("{}", message);
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn report(message: &str) {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let console = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("console")).unwrap();
+    let log: js_sys::Function =
+        js_sys::Reflect::get(&console, &JsValue::from_str("log")).unwrap().unchecked_into();
+    let _ = log.call1(&console, &JsValue::from_str(message));
+}
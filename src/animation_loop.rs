@@ -0,0 +1,162 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+use crate::fps_logger::*;
+use crate::time_source::*;
+use crate::*;
+
+/// Runs a callback on every animation frame using the browser's `requestAnimationFrame`.
+///
+/// The callback is passed the time since the previous frame, as measured by a `Stopwatch`, so
+/// pausing the stopwatch or changing its speed affects the deltas. Clones refer to the same loop,
+/// so the callback can capture a clone to stop it. The loop stops when every handle is dropped.
+pub struct AnimationLoop<S: TimeSource + 'static = DefaultClock> {
+    state: Rc<RefCell<State<S>>>,
+}
+
+struct State<S: TimeSource> {
+    callback: Option<Box<dyn FnMut(f64)>>,
+    stopwatch: Stopwatch<S>,
+    last_time: f64,
+    fps_logger: Option<FpsLogger>,
+    running: bool,
+    frame_request: Option<i32>,
+    closure: Option<Closure<dyn FnMut(f64)>>,
+}
+
+impl AnimationLoop {
+    /// Creates a stopped animation loop which calls `callback` with the time since the previous
+    /// frame, in seconds.
+    pub fn new(callback: impl FnMut(f64) + 'static) -> Self {
+        Self::with_stopwatch(Stopwatch::new(), callback)
+    }
+}
+
+impl<S: TimeSource + 'static> AnimationLoop<S> {
+    /// Like `new`, but measures the time between frames with the given stopwatch.
+    pub fn with_stopwatch(stopwatch: Stopwatch<S>, callback: impl FnMut(f64) + 'static) -> Self {
+        let state = Rc::new(RefCell::new(State {
+            callback: Some(Box::new(callback)),
+            last_time: stopwatch.get_time(),
+            stopwatch,
+            fps_logger: None,
+            running: false,
+            frame_request: None,
+            closure: None,
+        }));
+        let weak_state = Rc::downgrade(&state);
+        let closure = Closure::wrap(Box::new(move |_timestamp: f64| {
+            if let Some(state) = weak_state.upgrade() {
+                run_frame(&state);
+            }
+        }) as Box<dyn FnMut(f64)>);
+        state.borrow_mut().closure = Some(closure);
+        Self { state }
+    }
+
+    /// Starts the loop. If it was already running, this does nothing.
+    ///
+    /// The first frame's delta is measured from when the loop is started, so time spent stopped
+    /// isn't included.
+    pub fn start(&self) {
+        let mut state = self.state.borrow_mut();
+        if !state.running {
+            state.running = true;
+            state.last_time = state.stopwatch.get_time();
+            state.request_frame();
+        }
+    }
+
+    /// Stops the loop. If it was already stopped, this does nothing.
+    pub fn stop(&self) {
+        let mut state = self.state.borrow_mut();
+        state.running = false;
+        state.cancel_frame();
+    }
+
+    /// Returns whether the loop is running.
+    pub fn is_running(&self) -> bool {
+        self.state.borrow().running
+    }
+
+    /// Updates the given FPS logger every frame, or stops updating one if `fps_logger` is `None`.
+    pub fn set_fps_logger(&self, fps_logger: Option<FpsLogger>) {
+        self.state.borrow_mut().fps_logger = fps_logger;
+    }
+
+    /// Returns the FPS most recently computed by the loop's FPS logger, if it has one.
+    pub fn last_fps(&self) -> Option<i32> {
+        self.state.borrow().fps_logger.as_ref().map(|fps_logger| fps_logger.last_fps())
+    }
+
+    /// Calls `f` with the stopwatch which measures the time between frames, for instance to pause
+    /// it. This can be called from the loop's callback, but `f` mustn't use the loop itself.
+    pub fn update_stopwatch<R>(&self, f: impl FnOnce(&mut Stopwatch<S>) -> R) -> R {
+        f(&mut self.state.borrow_mut().stopwatch)
+    }
+}
+
+impl<S: TimeSource + 'static> Clone for AnimationLoop<S> {
+    fn clone(&self) -> Self {
+        Self { state: self.state.clone() }
+    }
+}
+
+impl<S: TimeSource> State<S> {
+    fn request_frame(&mut self) {
+        if self.frame_request.is_none() {
+            let closure = self.closure.as_ref().unwrap();
+            let request = window()
+                .expect("requestAnimationFrame is unavailable")
+                .request_animation_frame(closure.as_ref().unchecked_ref())
+                .expect("requestAnimationFrame failed");
+            self.frame_request = Some(request);
+        }
+    }
+
+    fn cancel_frame(&mut self) {
+        if let Some(request) = self.frame_request.take() {
+            if let Some(window) = window() {
+                let _ = window.cancel_animation_frame(request);
+            }
+        }
+    }
+}
+
+impl<S: TimeSource> Drop for State<S> {
+    fn drop(&mut self) {
+        self.cancel_frame();
+    }
+}
+
+fn run_frame<S: TimeSource>(state: &RefCell<State<S>>) {
+    let (delta, mut callback) = {
+        let mut state = state.borrow_mut();
+        state.frame_request = None;
+        if !state.running {
+            return;
+        }
+        let time = state.stopwatch.get_time();
+        let delta = time - state.last_time;
+        state.last_time = time;
+        if let Some(fps_logger) = &mut state.fps_logger {
+            fps_logger.update();
+        }
+        (delta, state.callback.take())
+    };
+
+    // The callback runs without the state borrowed, so it can stop or restart the loop.
+    if let Some(callback) = &mut callback {
+        callback(delta);
+    }
+
+    let mut state = state.borrow_mut();
+    state.callback = callback;
+    if state.running {
+        state.request_frame();
+    }
+}
@@ -0,0 +1,162 @@
+use crate::time_source::*;
+use crate::*;
+
+/// A timer which counts down from a given duration, such as a round timer or a bomb fuse.
+///
+/// Time is measured by a `Stopwatch`, so countdowns can be paused and sped up or slowed down.
+#[derive(Clone)]
+pub struct Countdown<S: TimeSource = DefaultClock> {
+    stopwatch: Stopwatch<S>,
+    duration: f64,
+    end_time: f64,
+    expiry_reported: bool,
+}
+
+impl Countdown {
+    /// Creates a countdown which expires after `duration` seconds.
+    pub fn new(duration: f64) -> Self {
+        Self::with_stopwatch(duration, Stopwatch::new())
+    }
+}
+
+impl<S: TimeSource> Countdown<S> {
+    /// Creates a countdown which expires once `stopwatch` has advanced by `duration`.
+    pub fn with_stopwatch(duration: f64, stopwatch: Stopwatch<S>) -> Self {
+        let end_time = stopwatch.get_time() + duration;
+        Self { stopwatch, duration, end_time, expiry_reported: false }
+    }
+
+    /// Returns the countdown's total duration, including any extensions.
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Returns the time left before the countdown expires, or 0 if it has expired.
+    pub fn remaining(&self) -> f64 {
+        (self.end_time - self.stopwatch.get_time()).max(0.0)
+    }
+
+    /// Returns whether the countdown has expired.
+    pub fn is_expired(&self) -> bool {
+        self.remaining() <= 0.0
+    }
+
+    /// Returns how far through the countdown is, from 0 when it starts to 1 when it expires.
+    pub fn progress(&self) -> f64 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (1.0 - self.remaining() / self.duration).clamp(0.0, 1.0)
+        }
+    }
+
+    /// Returns `true` the first time it's called after the countdown expires, and `false`
+    /// otherwise. Useful for reacting to expiry exactly once.
+    pub fn poll_expired(&mut self) -> bool {
+        if self.expiry_reported || !self.is_expired() {
+            false
+        } else {
+            self.expiry_reported = true;
+            true
+        }
+    }
+
+    /// Adds `secs` to the countdown. If this brings an expired countdown back above 0, it can
+    /// expire (and be reported by `poll_expired`) again.
+    pub fn extend(&mut self, secs: f64) {
+        self.duration += secs;
+        self.end_time += secs;
+        if !self.is_expired() {
+            self.expiry_reported = false;
+        }
+    }
+
+    /// Restarts the countdown from its full duration.
+    pub fn restart(&mut self) {
+        self.end_time = self.stopwatch.get_time() + self.duration;
+        self.expiry_reported = false;
+    }
+
+    /// Returns whether the countdown is paused.
+    pub fn paused(&self) -> bool {
+        self.stopwatch.paused()
+    }
+
+    /// Pauses the countdown. If the countdown was already paused, this does nothing.
+    pub fn pause(&mut self) {
+        self.stopwatch.pause();
+    }
+
+    /// Unpauses the countdown. If the countdown was already unpaused, this does nothing.
+    pub fn unpause(&mut self) {
+        self.stopwatch.unpause();
+    }
+
+    /// Toggles whether the countdown is paused.
+    pub fn toggle_pause(&mut self) {
+        self.stopwatch.toggle_pause();
+    }
+
+    /// Returns the speed at which the countdown runs.
+    pub fn speed(&self) -> f64 {
+        self.stopwatch.speed()
+    }
+
+    /// Changes the speed at which the countdown runs. See `Stopwatch::set_speed`.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.stopwatch.set_speed(speed);
+    }
+
+    /// Returns the underlying stopwatch.
+    pub fn stopwatch(&self) -> &Stopwatch<S> {
+        &self.stopwatch
+    }
+
+    /// Returns the underlying stopwatch mutably. Changing its time moves the countdown by the same
+    /// amount.
+    pub fn stopwatch_mut(&mut self) -> &mut Stopwatch<S> {
+        &mut self.stopwatch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry() {
+        let clock = ManualClock::new();
+        let mut countdown = Countdown::with_stopwatch(2.0, Stopwatch::with_source(clock.clone()));
+        clock.advance(1.0);
+        assert_eq!(countdown.remaining(), 1.0);
+        assert_eq!(countdown.progress(), 0.5);
+        assert!(!countdown.poll_expired());
+
+        clock.advance(1.5);
+        assert!(countdown.is_expired());
+        assert_eq!(countdown.remaining(), 0.0);
+        assert!(countdown.poll_expired());
+        assert!(!countdown.poll_expired());
+
+        countdown.extend(1.0);
+        assert_eq!(countdown.remaining(), 0.5);
+        clock.advance(1.0);
+        assert!(countdown.poll_expired());
+
+        countdown.restart();
+        assert_eq!(countdown.remaining(), 3.0);
+    }
+
+    #[test]
+    fn pause_and_speed() {
+        let clock = ManualClock::new();
+        let mut countdown = Countdown::with_stopwatch(2.0, Stopwatch::with_source(clock.clone()));
+        countdown.pause();
+        clock.advance(5.0);
+        assert_eq!(countdown.remaining(), 2.0);
+        countdown.unpause();
+        countdown.set_speed(2.0);
+        clock.advance(0.5);
+        assert_eq!(countdown.remaining(), 1.0);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::time_source::*;
+use crate::*;
+
+/// A frame whose delta exceeded a `DeltaClamp`'s maximum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hitch {
+    /// The frame's delta as measured by the stopwatch.
+    pub delta: f64,
+    /// The delta after clamping.
+    pub clamped_delta: f64,
+}
+
+/// Measures per-frame deltas from a `Stopwatch`, clamped to a maximum.
+///
+/// After a debugger breakpoint or a long load, the next frame's delta can be several seconds,
+/// which can make physics and animations misbehave. `DeltaClamp` limits each delta, counts the
+/// frames where that happened, and can optionally take the excess time back out of the
+/// stopwatch so its time doesn't leap forward either.
+pub struct DeltaClamp {
+    max_delta: f64,
+    rewind_excess: bool,
+    last_time: Option<f64>,
+    hitches: u64,
+    on_hitch: Option<Box<dyn FnMut(Hitch)>>,
+}
+
+impl DeltaClamp {
+    /// Creates a clamp which limits deltas to `max_delta` seconds.
+    pub fn new(max_delta: f64) -> Self {
+        assert!(max_delta >= 0.0, "the maximum delta can't be negative");
+        Self { max_delta, rewind_excess: false, last_time: None, hitches: 0, on_hitch: None }
+    }
+
+    /// Returns the maximum delta.
+    pub fn max_delta(&self) -> f64 {
+        self.max_delta
+    }
+
+    /// Sets whether time beyond the maximum delta is subtracted from the stopwatch. Defaults to
+    /// `false`.
+    pub fn set_rewind_excess(&mut self, rewind_excess: bool) {
+        self.rewind_excess = rewind_excess;
+    }
+
+    /// Calls `on_hitch` whenever a delta is clamped. Replaces any previous hook.
+    pub fn on_hitch(&mut self, on_hitch: impl FnMut(Hitch) + 'static) {
+        self.on_hitch = Some(Box::new(on_hitch));
+    }
+
+    /// Returns how many deltas have been clamped.
+    pub fn hitches(&self) -> u64 {
+        self.hitches
+    }
+
+    /// Returns the time since the previous update, clamped to the maximum delta. If the stopwatch
+    /// is running backwards, the delta is negative and its magnitude is clamped instead. The first
+    /// call only records the stopwatch's time and returns 0.
+    pub fn update<S: TimeSource>(&mut self, stopwatch: &mut Stopwatch<S>) -> f64 {
+        let time = stopwatch.get_time();
+        let delta = self.last_time.map_or(0.0, |last_time| time - last_time);
+        let clamped_delta = delta.clamp(-self.max_delta, self.max_delta);
+        let excess = delta - clamped_delta;
+        if excess == 0.0 {
+            self.last_time = Some(time);
+        } else {
+            self.hitches += 1;
+            if self.rewind_excess {
+                stopwatch.add_time(-excess);
+                self.last_time = Some(time - excess);
+            } else {
+                self.last_time = Some(time);
+            }
+            if let Some(on_hitch) = &mut self.on_hitch {
+                on_hitch(Hitch { delta, clamped_delta });
+            }
+        }
+        clamped_delta
+    }
+
+    /// Forgets the previous update's time, so the next update returns 0.
+    pub fn reset(&mut self) {
+        self.last_time = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn clamping() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        let mut clamp = DeltaClamp::new(0.25);
+        let hitches = Rc::new(RefCell::new(vec![]));
+        let hitches_handle = hitches.clone();
+        clamp.on_hitch(move |hitch| hitches_handle.borrow_mut().push(hitch));
+
+        assert_eq!(clamp.update(&mut stopwatch), 0.0);
+        clock.advance(0.125);
+        assert_eq!(clamp.update(&mut stopwatch), 0.125);
+        clock.advance(2.0);
+        assert_eq!(clamp.update(&mut stopwatch), 0.25);
+        assert_eq!(stopwatch.get_time(), 2.125);
+        clock.advance(0.125);
+        assert_eq!(clamp.update(&mut stopwatch), 0.125);
+
+        stopwatch.set_speed(-1.0);
+        clock.advance(1.0);
+        assert_eq!(clamp.update(&mut stopwatch), -0.25);
+        assert_eq!(clamp.hitches(), 2);
+        assert_eq!(
+            *hitches.borrow(),
+            [
+                Hitch { delta: 2.0, clamped_delta: 0.25 },
+                Hitch { delta: -1.0, clamped_delta: -0.25 },
+            ]
+        );
+
+        clamp.reset();
+        clock.advance(1.0);
+        assert_eq!(clamp.update(&mut stopwatch), 0.0);
+    }
+
+    #[test]
+    fn rewind_excess() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        let mut clamp = DeltaClamp::new(0.25);
+        clamp.set_rewind_excess(true);
+
+        clamp.update(&mut stopwatch);
+        clock.advance(2.0);
+        assert_eq!(clamp.update(&mut stopwatch), 0.25);
+        assert_eq!(stopwatch.get_time(), 0.25);
+        clock.advance(0.125);
+        assert_eq!(clamp.update(&mut stopwatch), 0.125);
+        assert_eq!(stopwatch.get_time(), 0.375);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+
+use crate::time_source::*;
+use crate::*;
+
+/// How a `DeltaSmoother` smooths frame deltas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    /// An exponential moving average. The value, between 0 and 1, is how much weight each new
+    /// delta gets; smaller values smooth more.
+    Ema(f64),
+    /// The median of the last given number of deltas, which ignores occasional outliers.
+    Median(usize),
+    /// Snaps deltas which are within `tolerance` of a whole number of display refreshes to exactly
+    /// that many refreshes. Works well with vsync.
+    SnapToRefresh { refresh_interval: f64, tolerance: f64 },
+}
+
+/// Smooths frame deltas from a `Stopwatch` to remove jitter.
+///
+/// Even with vsync, raw deltas jitter by a millisecond or so, which shows up as stutter in
+/// animations. Smoothed deltas don't add up to the true elapsed time on their own, so the
+/// smoother keeps track of the difference and gradually feeds it back in, meaning the sum of the
+/// smoothed deltas never drifts away from the stopwatch's time. Large hitches are best clamped
+/// first with a `DeltaClamp`, then passed to `smooth`.
+#[derive(Clone, Debug)]
+pub struct DeltaSmoother {
+    smoothing: Smoothing,
+    resync_rate: f64,
+    last_time: Option<f64>,
+    average: Option<f64>,
+    history: VecDeque<f64>,
+    /// The true elapsed time minus the sum of the smoothed deltas returned so far.
+    drift: f64,
+}
+
+impl DeltaSmoother {
+    /// Creates a smoother using the given strategy.
+    ///
+    /// Panics if an `Ema` weight isn't greater than 0 and at most 1, or if a `SnapToRefresh`
+    /// refresh interval isn't positive.
+    pub fn new(smoothing: Smoothing) -> Self {
+        match smoothing {
+            Smoothing::Ema(weight) => {
+                assert!(weight > 0.0 && weight <= 1.0, "the EMA weight must be in (0, 1]");
+            }
+            Smoothing::Median(_) => {}
+            Smoothing::SnapToRefresh { refresh_interval, .. } => {
+                assert!(refresh_interval > 0.0, "the refresh interval must be positive");
+            }
+        }
+        Self {
+            smoothing,
+            resync_rate: 0.1,
+            last_time: None,
+            average: None,
+            history: VecDeque::new(),
+            drift: 0.0,
+        }
+    }
+
+    /// Sets the fraction of the accumulated drift which is added back to each smoothed delta.
+    /// Higher values keep closer to the true time but let more jitter through. Defaults to 0.1.
+    pub fn set_resync_rate(&mut self, resync_rate: f64) {
+        self.resync_rate = resync_rate;
+    }
+
+    /// Returns the difference between the true elapsed time and the sum of the smoothed deltas
+    /// returned so far.
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Returns the smoothed time since the previous update. The first call only records the
+    /// stopwatch's time and returns 0.
+    pub fn update<S: TimeSource>(&mut self, stopwatch: &Stopwatch<S>) -> f64 {
+        let time = stopwatch.get_time();
+        match self.last_time.replace(time) {
+            None => 0.0,
+            Some(last_time) => self.smooth(time - last_time),
+        }
+    }
+
+    /// Smooths a delta measured elsewhere, for instance one returned by a `DeltaClamp`.
+    pub fn smooth(&mut self, delta: f64) -> f64 {
+        let smoothed = match self.smoothing {
+            Smoothing::Ema(weight) => {
+                let average =
+                    self.average.map_or(delta, |average| average + (delta - average) * weight);
+                self.average = Some(average);
+                average
+            }
+            Smoothing::Median(len) => {
+                self.history.push_back(delta);
+                while self.history.len() > len.max(1) {
+                    self.history.pop_front();
+                }
+                median(&self.history)
+            }
+            Smoothing::SnapToRefresh { refresh_interval, tolerance } => {
+                let refreshes = (delta / refresh_interval).round().max(1.0);
+                let snapped = refreshes * refresh_interval;
+                if (delta - snapped).abs() <= tolerance {
+                    snapped
+                } else {
+                    delta
+                }
+            }
+        };
+        let result = smoothed + (self.drift + delta - smoothed) * self.resync_rate;
+        self.drift += delta - result;
+        result
+    }
+
+    /// Forgets all previous deltas and any accumulated drift.
+    pub fn reset(&mut self) {
+        self.last_time = None;
+        self.average = None;
+        self.history.clear();
+        self.drift = 0.0;
+    }
+}
+
+fn median(values: &VecDeque<f64>) -> f64 {
+    let mut sorted: Vec<f64> = values.iter().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        sorted[mid]
+    } else {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smooth_all(smoother: &mut DeltaSmoother, deltas: &[f64]) -> Vec<f64> {
+        deltas.iter().map(|&delta| smoother.smooth(delta)).collect()
+    }
+
+    #[test]
+    fn strategies() {
+        let mut ema = DeltaSmoother::new(Smoothing::Ema(0.5));
+        ema.set_resync_rate(0.0);
+        assert_eq!(smooth_all(&mut ema, &[1.0, 0.0, 0.0]), [1.0, 0.5, 0.25]);
+        assert_eq!(ema.drift(), -0.75);
+
+        let mut median = DeltaSmoother::new(Smoothing::Median(3));
+        median.set_resync_rate(0.0);
+        assert_eq!(smooth_all(&mut median, &[0.25, 0.75, 0.5, 0.0]), [0.25, 0.5, 0.5, 0.5]);
+        median.smooth(f64::NAN);
+
+        let refresh_interval = 1.0 / 64.0;
+        let mut snap =
+            DeltaSmoother::new(Smoothing::SnapToRefresh { refresh_interval, tolerance: 0.002 });
+        snap.set_resync_rate(0.0);
+        assert_eq!(
+            smooth_all(&mut snap, &[0.016, 0.031, 0.02]),
+            [refresh_interval, 2.0 * refresh_interval, 0.02]
+        );
+    }
+
+    #[test]
+    fn drift() {
+        let clock = ManualClock::new();
+        let stopwatch = Stopwatch::with_source(clock.clone());
+        let mut smoother = DeltaSmoother::new(Smoothing::Median(5));
+        assert_eq!(smoother.update(&stopwatch), 0.0);
+        let mut total = 0.0;
+        for i in 0..100 {
+            clock.advance(if i % 10 == 0 { 0.05 } else { 0.016 });
+            total += smoother.update(&stopwatch);
+        }
+        assert!((total + smoother.drift() - stopwatch.get_time()).abs() < 1e-9);
+        assert!(smoother.drift().abs() < 0.05);
+
+        smoother.reset();
+        assert_eq!(smoother.drift(), 0.0);
+        assert_eq!(smoother.update(&stopwatch), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_weight() {
+        DeltaSmoother::new(Smoothing::Ema(0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_refresh_interval() {
+        DeltaSmoother::new(Smoothing::SnapToRefresh { refresh_interval: 0.0, tolerance: 0.001 });
+    }
+}
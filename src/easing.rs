@@ -0,0 +1,45 @@
+//! Easing curves for `Stopwatch::ramp_speed`.
+
+/// A curve describing how a speed ramp blends from its starting speed to its target speed.
+///
+/// Each curve maps progress through the ramp (from 0 to 1) to a blend factor (also from 0 to 1).
+/// The stopwatch integrates the curve analytically, so its time stays exact no matter how often
+/// it's sampled during a ramp.
+#[derive(Clone, Copy, Debug)]
+pub enum Easing {
+    /// Changes speed at a constant rate.
+    Linear,
+    /// Starts slowly and finishes quickly.
+    EaseIn,
+    /// Starts quickly and finishes slowly.
+    EaseOut,
+    /// Starts and finishes slowly (smoothstep).
+    EaseInOut,
+    /// A custom curve. `integral` must be the antiderivative of `curve`, with
+    /// `integral(0.0) == 0.0`.
+    Custom { curve: fn(f64) -> f64, integral: fn(f64) -> f64 },
+}
+
+impl Easing {
+    /// Returns the blend factor at progress `t`, which must be between 0 and 1.
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Custom { curve, .. } => curve(t),
+        }
+    }
+
+    /// Returns the integral of the curve from 0 to `t`, which must be between 0 and 1.
+    pub fn integral(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t * t / 2.0,
+            Easing::EaseIn => t * t * t / 3.0,
+            Easing::EaseOut => t - (1.0 - (1.0 - t).powi(3)) / 3.0,
+            Easing::EaseInOut => t * t * t - t * t * t * t / 2.0,
+            Easing::Custom { integral, .. } => integral(t),
+        }
+    }
+}
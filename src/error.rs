@@ -0,0 +1,34 @@
+use std::error::Error;
+use std::fmt;
+
+/// An error returned by the fallible `Stopwatch` methods.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum StopwatchError {
+    /// The given speed was NaN or infinite.
+    InvalidSpeed(f64),
+    /// The time source couldn't be read.
+    ClockUnavailable,
+    /// The operation requires the stopwatch to be running, but it's paused.
+    Paused,
+    /// The stopwatch will never reach the requested time at its current speed.
+    Unreachable,
+    /// There's no `document` to watch, for instance because the code is running in a Web Worker.
+    NoDocument,
+}
+
+impl fmt::Display for StopwatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopwatchError::InvalidSpeed(speed) => write!(f, "invalid stopwatch speed: {}", speed),
+            StopwatchError::ClockUnavailable => write!(f, "the time source is unavailable"),
+            StopwatchError::Paused => write!(f, "the stopwatch is paused"),
+            StopwatchError::Unreachable => {
+                write!(f, "the stopwatch will never reach the requested time")
+            }
+            StopwatchError::NoDocument => write!(f, "there is no document"),
+        }
+    }
+}
+
+impl Error for StopwatchError {}
@@ -0,0 +1,164 @@
+use crate::time_source::*;
+use crate::*;
+
+/// What a `FixedTimestep` does with partially accumulated time while its stopwatch is paused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseBehavior {
+    /// Keeps the accumulated time, so the interpolation alpha stays where it was when paused.
+    Hold,
+    /// Discards the accumulated time, so simulation restarts on a step boundary when unpaused.
+    Reset,
+}
+
+/// Drives a fixed-timestep simulation loop from a `Stopwatch`.
+///
+/// Call `update` once per frame to find out how many simulation steps to run, then render using
+/// `alpha` to interpolate between the last two simulation states. Time is measured in stopwatch
+/// time, so pausing the stopwatch stops the simulation and changing its speed changes how many
+/// steps run per frame. Time running backwards is ignored.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: f64,
+    max_steps: u32,
+    pause_behavior: PauseBehavior,
+    scale_max_steps: bool,
+    accumulator: f64,
+    last_time: Option<f64>,
+    dropped_time: f64,
+}
+
+impl FixedTimestep {
+    /// Creates a driver which runs steps of `step` seconds of stopwatch time, and at most 8 steps
+    /// per frame.
+    pub fn new(step: f64) -> Self {
+        assert!(step > 0.0, "the timestep must be positive");
+        Self {
+            step,
+            max_steps: 8,
+            pause_behavior: PauseBehavior::Hold,
+            scale_max_steps: false,
+            accumulator: 0.0,
+            last_time: None,
+            dropped_time: 0.0,
+        }
+    }
+
+    /// Returns the length of each step.
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Sets the maximum number of steps `update` returns. Time beyond that is dropped, so that a
+    /// simulation which can't keep up doesn't fall further and further behind.
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    /// Sets what happens to accumulated time while the stopwatch is paused. Defaults to
+    /// `PauseBehavior::Hold`.
+    pub fn set_pause_behavior(&mut self, pause_behavior: PauseBehavior) {
+        self.pause_behavior = pause_behavior;
+    }
+
+    /// Sets whether the maximum number of steps is multiplied by the stopwatch's speed when it's
+    /// faster than 1, so that fast-forwarding isn't limited by the clamp. Defaults to `false`.
+    pub fn set_scale_max_steps(&mut self, scale_max_steps: bool) {
+        self.scale_max_steps = scale_max_steps;
+    }
+
+    /// Accumulates the time since the last update and returns how many steps to run this frame.
+    /// The first call only records the stopwatch's time and returns 0.
+    pub fn update<S: TimeSource>(&mut self, stopwatch: &Stopwatch<S>) -> u32 {
+        let time = stopwatch.get_time();
+        if let Some(last_time) = self.last_time.replace(time) {
+            self.accumulator += (time - last_time).max(0.0);
+        }
+        if stopwatch.paused() && self.pause_behavior == PauseBehavior::Reset {
+            self.accumulator = 0.0;
+        }
+
+        let mut max_steps = self.max_steps;
+        if self.scale_max_steps {
+            max_steps = (max_steps as f64 * stopwatch.speed().abs().max(1.0)).ceil() as u32;
+        }
+        let steps = (self.accumulator / self.step).floor();
+        if steps > max_steps as f64 {
+            self.accumulator -= max_steps as f64 * self.step;
+            let excess = self.accumulator - self.accumulator % self.step;
+            self.dropped_time += excess;
+            self.accumulator -= excess;
+            max_steps
+        } else {
+            self.accumulator -= steps * self.step;
+            steps as u32
+        }
+    }
+
+    /// Returns how far the simulation is between the last step and the next one, from 0 to 1.
+    /// Rendering should interpolate between the previous and current states using this.
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    /// Returns the total time dropped because more than the maximum number of steps were due.
+    pub fn dropped_time(&self) -> f64 {
+        self.dropped_time
+    }
+
+    /// Discards any accumulated time. The next `update` starts measuring from scratch.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last_time = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_and_alpha() {
+        let clock = ManualClock::new();
+        let stopwatch = Stopwatch::with_source(clock.clone());
+        let mut timestep = FixedTimestep::new(0.25);
+        assert_eq!(timestep.update(&stopwatch), 0);
+        clock.advance(0.625);
+        assert_eq!(timestep.update(&stopwatch), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+    }
+
+    #[test]
+    fn clamp() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.update(&stopwatch);
+        clock.advance(10.125);
+        assert_eq!(timestep.update(&stopwatch), 8);
+        assert_eq!(timestep.dropped_time(), 8.0);
+        assert_eq!(timestep.alpha(), 0.5);
+
+        stopwatch.set_speed(4.0);
+        timestep.set_scale_max_steps(true);
+        clock.advance(10.0);
+        assert_eq!(timestep.update(&stopwatch), 32);
+    }
+
+    #[test]
+    fn pause_behavior() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.update(&stopwatch);
+        clock.advance(0.125);
+        timestep.update(&stopwatch);
+        stopwatch.pause();
+        clock.advance(1.0);
+        assert_eq!(timestep.update(&stopwatch), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+
+        timestep.set_pause_behavior(PauseBehavior::Reset);
+        timestep.update(&stopwatch);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
@@ -1,9 +1,10 @@
 use log::*;
 
+use crate::time_source::*;
 use crate::*;
 
 /// Logs the current FPS (with the `log` crate) at a specified interval.
-pub struct FpsLogger {
+pub struct FpsLogger<S: TimeSource = DefaultClock> {
     start_time: f64,
     prev_time: f64,
     seconds_between_logs: f64,
@@ -11,13 +12,19 @@ pub struct FpsLogger {
     total_frames: i64,
     frames: i64,
     last_fps: i32,
-    stopwatch: Stopwatch,
+    stopwatch: Stopwatch<S>,
 }
 
 impl FpsLogger {
     /// Creates a new `FpsLogger`. `seconds_between_logs` is the interval at which it logs the FPS.
     pub fn new(seconds_between_logs: f64) -> Self {
-        let stopwatch = Stopwatch::new();
+        Self::with_stopwatch(seconds_between_logs, Stopwatch::new())
+    }
+}
+
+impl<S: TimeSource> FpsLogger<S> {
+    /// Like `new`, but measures frame times with the given stopwatch.
+    pub fn with_stopwatch(seconds_between_logs: f64, stopwatch: Stopwatch<S>) -> Self {
         let start_time = stopwatch.get_time();
         Self {
             start_time,
@@ -57,3 +64,40 @@ impl FpsLogger {
         self.last_fps
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_interval() {
+        let clock = ManualClock::new();
+        let mut logger = FpsLogger::with_stopwatch(1.0, Stopwatch::with_source(clock.clone()));
+        for _ in 0..4 {
+            assert_eq!(logger.last_fps(), 0);
+            clock.advance(0.25);
+            logger.update();
+        }
+        assert_eq!(logger.last_fps(), 4);
+
+        for _ in 0..7 {
+            clock.advance(0.125);
+            logger.update();
+            assert_eq!(logger.last_fps(), 4);
+        }
+        clock.advance(0.125);
+        logger.update();
+        assert_eq!(logger.last_fps(), 8);
+    }
+
+    #[test]
+    fn paused_stopwatch() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        stopwatch.pause();
+        let mut logger = FpsLogger::with_stopwatch(1.0, stopwatch);
+        clock.advance(2.0);
+        logger.update();
+        assert_eq!(logger.last_fps(), 0);
+    }
+}
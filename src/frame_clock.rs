@@ -0,0 +1,105 @@
+use crate::time_source::*;
+use crate::*;
+
+/// A `Stopwatch` whose time only changes once per frame.
+///
+/// Reading a stopwatch several times in one frame gives slightly different results each time,
+/// which can make systems drift out of sync. `FrameClock` latches the stopwatch's time when
+/// `begin_frame` is called, and `time` returns that value until the next frame. The stopwatch can
+/// still be read directly with `live_time`.
+#[derive(Clone)]
+pub struct FrameClock<S: TimeSource = DefaultClock> {
+    stopwatch: Stopwatch<S>,
+    time: f64,
+    delta: f64,
+    frame: u64,
+}
+
+impl FrameClock {
+    /// Creates a new frame clock with the current time set to 0.
+    pub fn new() -> Self {
+        Self::with_stopwatch(Stopwatch::new())
+    }
+}
+
+impl<S: TimeSource> FrameClock<S> {
+    /// Creates a frame clock which latches the given stopwatch's time.
+    pub fn with_stopwatch(stopwatch: Stopwatch<S>) -> Self {
+        let time = stopwatch.get_time();
+        Self { stopwatch, time, delta: 0.0, frame: 0 }
+    }
+
+    /// Starts a new frame by latching the stopwatch's current time, and returns the time since the
+    /// previous frame. Should be called once at the start of each frame.
+    pub fn begin_frame(&mut self) -> f64 {
+        let time = self.stopwatch.get_time();
+        self.delta = time - self.time;
+        self.time = time;
+        self.frame += 1;
+        self.delta
+    }
+
+    /// Returns the stopwatch's time as of the last `begin_frame`.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the time between the last two calls to `begin_frame`. For the first frame, this is
+    /// the time since the frame clock was created.
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    /// Returns how many times `begin_frame` has been called.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns the stopwatch's current time, ignoring the latched value.
+    pub fn live_time(&self) -> f64 {
+        self.stopwatch.get_time()
+    }
+
+    /// Returns the underlying stopwatch.
+    pub fn stopwatch(&self) -> &Stopwatch<S> {
+        &self.stopwatch
+    }
+
+    /// Returns the underlying stopwatch mutably. Changes to its time show up in `time` from the
+    /// next frame onwards.
+    pub fn stopwatch_mut(&mut self) -> &mut Stopwatch<S> {
+        &mut self.stopwatch
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latching() {
+        let clock = ManualClock::new();
+        let mut frame_clock = FrameClock::with_stopwatch(Stopwatch::with_source(clock.clone()));
+        clock.advance(0.5);
+        assert_eq!(frame_clock.time(), 0.0);
+        assert_eq!(frame_clock.live_time(), 0.5);
+
+        assert_eq!(frame_clock.begin_frame(), 0.5);
+        clock.advance(0.25);
+        assert_eq!(frame_clock.time(), 0.5);
+        assert_eq!(frame_clock.delta(), 0.5);
+        assert_eq!(frame_clock.live_time(), 0.75);
+
+        frame_clock.stopwatch_mut().add_time(1.0);
+        assert_eq!(frame_clock.time(), 0.5);
+        assert_eq!(frame_clock.begin_frame(), 1.25);
+        assert_eq!(frame_clock.time(), 1.75);
+        assert_eq!(frame_clock.frame(), 2);
+    }
+}
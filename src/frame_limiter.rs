@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use crate::time_source::*;
+
+/// Limits a loop to a target frame rate.
+///
+/// `std::thread::sleep` often oversleeps by anywhere from tens of microseconds to a millisecond,
+/// which is too imprecise for frame pacing on its own. `FrameLimiter` sleeps until shortly before
+/// each frame's deadline, then busy-waits for the rest. It measures how much each sleep overshoots
+/// and adapts the margin it leaves for the busy-wait accordingly.
+///
+/// Deadlines are absolute, so small errors in one frame don't accumulate into drift.
+pub struct FrameLimiter<S: TimeSource = DefaultClock> {
+    source: S,
+    frame_nanos: i64,
+    deadline: Option<i64>,
+    sleep_margin: i64,
+    overshoot: i64,
+}
+
+impl FrameLimiter {
+    /// Creates a frame limiter targeting `fps` frames per second.
+    pub fn new(fps: f64) -> Self {
+        Self::with_source(DefaultClock::default(), fps)
+    }
+}
+
+impl<S: TimeSource> FrameLimiter<S> {
+    /// Like `new`, but reads time from `source`.
+    pub fn with_source(source: S, fps: f64) -> Self {
+        let frame_nanos = frame_nanos(fps);
+        Self { source, frame_nanos, deadline: None, sleep_margin: 1_000_000, overshoot: 0 }
+    }
+
+    /// Returns the target frame rate.
+    pub fn target_fps(&self) -> f64 {
+        1.0 / nanos_to_secs(self.frame_nanos)
+    }
+
+    /// Changes the target frame rate, starting from the next frame.
+    pub fn set_target_fps(&mut self, fps: f64) {
+        self.frame_nanos = frame_nanos(fps);
+    }
+
+    /// Waits until the end of the current frame. Should be called once per frame.
+    ///
+    /// If the frame has already run past its deadline, this returns immediately and the next
+    /// frame is timed from now rather than trying to catch up.
+    pub fn wait(&mut self) {
+        let now = self.source.now();
+        let deadline = match self.deadline {
+            None => now + self.frame_nanos,
+            Some(deadline) => (deadline + self.frame_nanos).max(now),
+        };
+
+        let wake_time = deadline - self.sleep_margin;
+        if wake_time > now {
+            self.source.sleep(Duration::from_nanos((wake_time - now) as u64));
+            let oversleep = (self.source.now() - wake_time).max(0);
+            self.adapt_margin(oversleep);
+        }
+        self.source.spin_until(deadline);
+
+        self.overshoot = self.source.now() - deadline;
+        self.deadline = Some(deadline);
+    }
+
+    /// Returns how long after its deadline the last frame ended.
+    pub fn overshoot(&self) -> Duration {
+        Duration::from_nanos(self.overshoot.max(0) as u64)
+    }
+
+    /// Returns how long before each deadline the limiter currently stops sleeping and starts
+    /// busy-waiting.
+    pub fn sleep_margin(&self) -> Duration {
+        Duration::from_nanos(self.sleep_margin as u64)
+    }
+
+    /// Grows the margin immediately when a sleep overshoots it, and otherwise lets it decay slowly
+    /// towards the observed oversleep so that busy-waiting doesn't waste more CPU than needed.
+    fn adapt_margin(&mut self, oversleep: i64) {
+        let target = oversleep + oversleep / 4;
+        self.sleep_margin = if target > self.sleep_margin {
+            target
+        } else {
+            self.sleep_margin - (self.sleep_margin - target) / 16
+        };
+        self.sleep_margin = self.sleep_margin.min(self.frame_nanos);
+    }
+}
+
+fn frame_nanos(fps: f64) -> i64 {
+    assert!(fps > 0.0, "the target frame rate must be positive");
+    secs_to_nanos(1.0 / fps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadlines() {
+        let clock = ManualClock::new();
+        let mut limiter = FrameLimiter::with_source(clock.clone(), 50.0);
+        limiter.wait();
+        assert_eq!(clock.time(), 0.02);
+        clock.advance(0.005);
+        limiter.wait();
+        assert_eq!(clock.time(), 0.04);
+        assert_eq!(limiter.overshoot(), Duration::ZERO);
+
+        // A late frame times the next one from now rather than catching up.
+        clock.advance(0.05);
+        limiter.wait();
+        assert_eq!(clock.time(), 0.09);
+        limiter.wait();
+        assert_eq!(clock.time(), 0.11);
+    }
+
+    #[test]
+    fn sleep_margin() {
+        let clock = ManualClock::new();
+        let mut limiter = FrameLimiter::with_source(clock, 50.0);
+        limiter.adapt_margin(4_000_000);
+        assert_eq!(limiter.sleep_margin(), Duration::from_millis(5));
+        limiter.adapt_margin(0);
+        assert!(limiter.sleep_margin() < Duration::from_millis(5));
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use std::cell::RefCell;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use std::rc::Rc;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use crate::animation_loop::*;
+use crate::fixed_timestep::*;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::frame_limiter::*;
+use crate::shared::*;
+use crate::time_source::*;
+
+/// A main loop which works the same way on desktop and on the web.
+///
+/// Each frame, `update` is called with a fixed timestep as many times as a `FixedTimestep` says
+/// are due, then `render` is called with the interpolation alpha. On desktop the loop runs on the
+/// current thread and is paced by a `FrameLimiter`; on the web it's driven by
+/// `requestAnimationFrame`. Time is measured by a `SharedStopwatch`, so pausing it or changing its
+/// speed (for instance through a clone captured by `update`) affects the simulation.
+pub struct GameLoop<S: TimeSource + 'static = DefaultClock> {
+    stopwatch: SharedStopwatch<S>,
+    timestep: FixedTimestep,
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    target_fps: Option<f64>,
+    stopped: Arc<AtomicBool>,
+}
+
+/// Stops a running `GameLoop`. Created by `GameLoop::stop_handle`.
+#[derive(Clone, Debug)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// Stops the loop after the current frame.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl GameLoop {
+    /// Creates a loop which updates the simulation in steps of `step` seconds.
+    pub fn new(step: f64) -> Self {
+        Self::with_stopwatch(SharedStopwatch::new(), step)
+    }
+}
+
+impl<S: TimeSource + 'static> GameLoop<S> {
+    /// Like `new`, but measures time with the given stopwatch.
+    pub fn with_stopwatch(stopwatch: SharedStopwatch<S>, step: f64) -> Self {
+        Self {
+            stopwatch,
+            timestep: FixedTimestep::new(step),
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            target_fps: Some(60.0),
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns the stopwatch which drives the loop.
+    pub fn stopwatch(&self) -> &SharedStopwatch<S> {
+        &self.stopwatch
+    }
+
+    /// Returns the loop's `FixedTimestep`, for instance to change its maximum number of steps.
+    pub fn timestep_mut(&mut self) -> &mut FixedTimestep {
+        &mut self.timestep
+    }
+
+    /// Sets the frame rate the loop is limited to on desktop, or removes the limit if `fps` is
+    /// `None`. Defaults to 60. Without a limit, the loop runs as fast as it can, which is only
+    /// appropriate if `render` blocks on vsync.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn set_target_fps(&mut self, fps: Option<f64>) {
+        self.target_fps = fps;
+    }
+
+    /// Returns a handle which can stop the loop, from inside or outside its callbacks.
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle { stopped: self.stopped.clone() }
+    }
+
+    /// Runs the loop until it's stopped. `update` is called with the length of each step, and
+    /// `render` with the interpolation alpha between the last two steps.
+    ///
+    /// On desktop, this blocks the current thread until the loop is stopped.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn run(
+        mut self,
+        mut update: impl FnMut(f64) + 'static,
+        mut render: impl FnMut(f64) + 'static,
+    ) {
+        let mut frame_limiter = self.target_fps.map(FrameLimiter::new);
+        while !self.stopped.load(Ordering::SeqCst) {
+            self.frame(&mut update, &mut render);
+            if let Some(frame_limiter) = &mut frame_limiter {
+                frame_limiter.wait();
+            }
+        }
+    }
+
+    /// Runs the loop until it's stopped. `update` is called with the length of each step, and
+    /// `render` with the interpolation alpha between the last two steps.
+    ///
+    /// On the web, this returns immediately and the loop keeps running on animation frames.
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn run(
+        mut self,
+        mut update: impl FnMut(f64) + 'static,
+        mut render: impl FnMut(f64) + 'static,
+    ) {
+        // The animation loop keeps itself alive through this reference until the game loop is
+        // stopped.
+        let animation_loop = Rc::new(RefCell::new(None::<AnimationLoop>));
+        let self_reference = animation_loop.clone();
+        let new_loop = AnimationLoop::new(move |_delta| {
+            if self.stopped.load(Ordering::SeqCst) {
+                if let Some(animation_loop) = self_reference.borrow_mut().take() {
+                    animation_loop.stop();
+                }
+            } else {
+                self.frame(&mut update, &mut render);
+            }
+        });
+        new_loop.start();
+        *animation_loop.borrow_mut() = Some(new_loop);
+    }
+
+    fn frame(&mut self, update: &mut impl FnMut(f64), render: &mut impl FnMut(f64)) {
+        let timestep = &mut self.timestep;
+        let steps = self.stopwatch.with(|stopwatch| timestep.update(stopwatch));
+        for _ in 0..steps {
+            update(self.timestep.step());
+            if self.stopped.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        render(self.timestep.alpha());
+    }
+}
+
+#[cfg(all(test, not(all(target_arch = "wasm32", target_os = "unknown"))))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn run() {
+        let clock = ManualClock::new();
+        let stopwatch = SharedStopwatch::from_stopwatch(Stopwatch::with_source(clock.clone()));
+        let mut game_loop = GameLoop::with_stopwatch(stopwatch, 0.25);
+        game_loop.set_target_fps(None);
+        let stop_handle = game_loop.stop_handle();
+
+        let updates = Arc::new(Mutex::new(vec![]));
+        let alphas = Arc::new(Mutex::new(vec![]));
+        let (updates_handle, alphas_handle) = (updates.clone(), alphas.clone());
+        game_loop.run(
+            move |step| updates_handle.lock().unwrap().push(step),
+            move |alpha| {
+                let mut alphas = alphas_handle.lock().unwrap();
+                alphas.push(alpha);
+                if alphas.len() == 4 {
+                    stop_handle.stop();
+                }
+                clock.advance(0.375);
+            },
+        );
+        assert_eq!(*updates.lock().unwrap(), [0.25; 4]);
+        assert_eq!(*alphas.lock().unwrap(), [0.0, 0.5, 0.0, 0.5]);
+    }
+}
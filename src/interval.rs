@@ -0,0 +1,188 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::shared::*;
+use crate::time_source::*;
+
+/// What an `Interval` does when ticks are missed, for instance because the task polling it was
+/// busy or the stopwatch's time was advanced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Yields all missed ticks immediately, one after another, to catch up.
+    Burst,
+    /// Yields one tick immediately, then schedules the following ticks a full period after it.
+    Delay,
+    /// Yields one tick immediately, then skips ahead to the next tick on the original schedule.
+    Skip,
+}
+
+/// A stream of ticks at a fixed period of stopwatch time. Created by `SharedStopwatch::interval`.
+///
+/// Each tick yields the stopwatch time it was scheduled for. Ticks follow the stopwatch's speed,
+/// and no ticks happen while it's paused or running backwards. If it's rewound, the following
+/// ticks stay on the same schedule, resuming from the first tick after the time it was rewound to.
+pub struct Interval<S: TimeSource = DefaultClock> {
+    stopwatch: SharedStopwatch<S>,
+    period: f64,
+    next_tick: f64,
+    missed_tick_behavior: MissedTickBehavior,
+    sleep: Option<SleepUntil<S>>,
+}
+
+impl<S: TimeSource> SharedStopwatch<S> {
+    /// Returns a stream which ticks every `period` seconds of stopwatch time. The first tick
+    /// happens immediately. Missed ticks are handled with `MissedTickBehavior::Burst` by default.
+    pub fn interval(&self, period: f64) -> Interval<S> {
+        assert!(period > 0.0, "the period must be positive");
+        Interval {
+            stopwatch: self.clone(),
+            period,
+            next_tick: self.get_time(),
+            missed_tick_behavior: MissedTickBehavior::Burst,
+            sleep: None,
+        }
+    }
+}
+
+impl<S: TimeSource> Interval<S> {
+    /// Returns the interval's period.
+    pub fn period(&self) -> f64 {
+        self.period
+    }
+
+    /// Returns how the interval handles missed ticks.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Sets how the interval handles missed ticks.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    /// Reschedules the next tick to a full period from now.
+    pub fn reset(&mut self) {
+        self.next_tick = self.stopwatch.get_time() + self.period;
+        self.sleep = None;
+    }
+
+    /// Waits for the next tick, and returns the stopwatch time it was scheduled for.
+    pub async fn tick(&mut self) -> f64 {
+        std::future::poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Polls for the next tick, returning the stopwatch time it was scheduled for.
+    pub fn poll_tick(&mut self, cx: &mut Context) -> Poll<f64> {
+        let stopwatch = &self.stopwatch;
+        let now = stopwatch.get_time();
+        if self.next_tick - now > self.period {
+            // The stopwatch was rewound, so bring the next tick back to within a period of it.
+            self.next_tick -= self.period * ((self.next_tick - now) / self.period).floor();
+            self.sleep = None;
+        }
+        let next_tick = self.next_tick;
+        let sleep = self.sleep.get_or_insert_with(|| stopwatch.sleep_until_forwards(next_tick));
+        if sleep.poll_nanos(cx).is_pending() {
+            return Poll::Pending;
+        }
+        self.sleep = None;
+
+        let tick = self.next_tick;
+        let now = self.stopwatch.get_time();
+        self.next_tick = if now <= tick + self.period {
+            tick + self.period
+        } else {
+            match self.missed_tick_behavior {
+                MissedTickBehavior::Burst => tick + self.period,
+                MissedTickBehavior::Delay => now + self.period,
+                MissedTickBehavior::Skip => {
+                    tick + self.period * (((now - tick) / self.period).floor() + 1.0)
+                }
+            }
+        };
+        Poll::Ready(tick)
+    }
+}
+
+impl<S: TimeSource> Stream for Interval<S> {
+    type Item = f64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<f64>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+
+    use super::*;
+    use crate::*;
+
+    fn poll(interval: &mut Interval<ManualClock>) -> Poll<f64> {
+        interval.poll_tick(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn interval(behavior: MissedTickBehavior) -> (ManualClock, Interval<ManualClock>) {
+        let clock = ManualClock::new();
+        let stopwatch = SharedStopwatch::from_stopwatch(Stopwatch::with_source(clock.clone()));
+        let mut interval = stopwatch.interval(1.0);
+        interval.set_missed_tick_behavior(behavior);
+        assert_eq!(poll(&mut interval), Poll::Ready(0.0));
+        assert_eq!(poll(&mut interval), Poll::Pending);
+        clock.advance(3.5);
+        (clock, interval)
+    }
+
+    #[test]
+    fn burst() {
+        let (_, mut interval) = interval(MissedTickBehavior::Burst);
+        assert_eq!(poll(&mut interval), Poll::Ready(1.0));
+        assert_eq!(poll(&mut interval), Poll::Ready(2.0));
+        assert_eq!(poll(&mut interval), Poll::Ready(3.0));
+        assert_eq!(poll(&mut interval), Poll::Pending);
+    }
+
+    #[test]
+    fn delay() {
+        let (clock, mut interval) = interval(MissedTickBehavior::Delay);
+        assert_eq!(poll(&mut interval), Poll::Ready(1.0));
+        clock.advance(0.75);
+        assert_eq!(poll(&mut interval), Poll::Pending);
+        clock.advance(0.25);
+        assert_eq!(poll(&mut interval), Poll::Ready(4.5));
+    }
+
+    #[test]
+    fn skip() {
+        let (clock, mut interval) = interval(MissedTickBehavior::Skip);
+        assert_eq!(poll(&mut interval), Poll::Ready(1.0));
+        assert_eq!(poll(&mut interval), Poll::Pending);
+        clock.advance(0.5);
+        assert_eq!(poll(&mut interval), Poll::Ready(4.0));
+    }
+
+    #[test]
+    fn rewind() {
+        let clock = ManualClock::new();
+        let stopwatch = SharedStopwatch::from_stopwatch(Stopwatch::with_source(clock.clone()));
+        let mut interval = stopwatch.interval(1.0);
+        clock.advance(0.5);
+        assert_eq!(poll(&mut interval), Poll::Ready(0.0));
+
+        stopwatch.set_speed(-1.0);
+        for _ in 0..10 {
+            assert_eq!(poll(&mut interval), Poll::Pending);
+        }
+        clock.advance(2.0);
+        assert_eq!(poll(&mut interval), Poll::Pending);
+
+        stopwatch.set_speed(1.0);
+        clock.advance(0.25);
+        assert_eq!(poll(&mut interval), Poll::Pending);
+        clock.advance(0.5);
+        assert_eq!(poll(&mut interval), Poll::Ready(-1.0));
+    }
+}
@@ -0,0 +1,10 @@
+//! Helpers for working with whichever JavaScript global scope the crate is running in.
+
+use wasm_bindgen::JsValue;
+
+/// Returns `target[name]`, or `None` if it's missing, `undefined` or `null`.
+pub(crate) fn property(target: &JsValue, name: &str) -> Option<JsValue> {
+    js_sys::Reflect::get(target, &JsValue::from_str(name))
+        .ok()
+        .filter(|value| !value.is_undefined() && !value.is_null())
+}
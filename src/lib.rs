@@ -1,48 +1,167 @@
 //! A simple stopwatch for games and similar applications.
 
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub mod animation_loop;
+pub mod countdown;
+pub mod delta_clamp;
+pub mod delta_smoother;
+pub mod easing;
+mod error;
+pub mod fixed_timestep;
 pub mod fps_logger;
+pub mod frame_clock;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod frame_limiter;
+pub mod game_loop;
+#[cfg(not(target_os = "wasi"))]
+pub mod interval;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod js;
+pub mod scheduler;
+pub mod shared;
+pub mod time_source;
+#[cfg(not(target_os = "wasi"))]
+mod timer;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub mod visibility;
 
-#[cfg(target_arch = "wasm32")]
-use web_sys::*;
+use std::ops::{AddAssign, SubAssign};
+use std::time::Duration;
+
+use easing::*;
+pub use error::*;
+use time_source::*;
 
 #[derive(Clone)]
-pub struct Stopwatch {
-    start_time: f64,
-    paused_at: Option<f64>,
+pub struct Stopwatch<S: TimeSource = DefaultClock> {
+    /// The stopwatch's time in nanoseconds as of `running_since`.
+    elapsed: i64,
+    /// The source's time when `elapsed` was last updated, or `None` if the stopwatch is paused.
+    running_since: Option<i64>,
+    /// The speed as of `running_since`. While ramping, this is the ramp's starting speed.
     speed: f64,
+    ramp: Option<SpeedRamp>,
+    source: S,
+}
+
+#[derive(Clone, Copy)]
+struct SpeedRamp {
+    target: f64,
+    easing: Easing,
+    /// The ramp's length in nanoseconds of the source's time.
+    duration: i64,
+    /// How far into the ramp the stopwatch was as of `running_since`.
+    progress: i64,
+}
+
+impl SpeedRamp {
+    fn speed_at(&self, start_speed: f64, progress: i64) -> f64 {
+        if progress >= self.duration {
+            self.target
+        } else {
+            let t = progress as f64 / self.duration as f64;
+            start_speed + (self.target - start_speed) * self.easing.apply(t)
+        }
+    }
+
+    /// Integrates the speed over `raw_elapsed` nanoseconds of the source's time, starting from
+    /// `self.progress`.
+    fn integrate(&self, start_speed: f64, raw_elapsed: i64) -> f64 {
+        let duration = self.duration as f64;
+        let start = self.progress as f64;
+        let end = start + raw_elapsed as f64;
+        let ramp_end = end.min(duration);
+        let mut total = 0.0;
+        if start < ramp_end {
+            let eased = self.easing.integral(ramp_end / duration)
+                - self.easing.integral(start / duration);
+            total += start_speed * (ramp_end - start)
+                + (self.target - start_speed) * duration * eased;
+        }
+        if end > duration {
+            total += (end - start.max(duration)) * self.target;
+        }
+        total
+    }
 }
 
-/// A stopwatch which tracks time in seconds.
 impl Stopwatch {
     /// Creates a new stopwatch with the current time set to 0.
     pub fn new() -> Self {
         Self::with_speed(1.0)
     }
 
+    /// Like `new`, but returns an error instead of panicking if the clock is unavailable.
+    pub fn try_new() -> Result<Self, StopwatchError> {
+        Self::try_with_speed(1.0)
+    }
+
     /// Creates a stopwatch which advances the given amount every second.
     ///
     /// For instance, `Stopwatch::with_speed(1.0/60.0)` creates a stopwatch which uses
-    /// minutes as the time unit instead of seconds.
+    /// minutes as the time unit instead of seconds. A speed of 0 creates a stopwatch which is
+    /// frozen until its speed is changed, and a negative speed makes it run backwards.
+    ///
+    /// Panics if `speed` is NaN or infinite, or if the clock is unavailable.
     pub fn with_speed(speed: f64) -> Self {
-        let cur_time = Self::get_raw_time();
-        Self { start_time: cur_time, paused_at: None, speed }
+        Self::with_source_and_speed(DefaultClock::default(), speed)
+    }
+
+    /// Like `with_speed`, but returns an error instead of panicking.
+    pub fn try_with_speed(speed: f64) -> Result<Self, StopwatchError> {
+        Self::try_with_source_and_speed(DefaultClock::default(), speed)
+    }
+}
+
+/// A stopwatch which tracks time in seconds.
+///
+/// Time is stored internally as integer nanoseconds, so precision doesn't degrade as the
+/// stopwatch or the underlying clock runs for a long time.
+impl<S: TimeSource> Stopwatch<S> {
+    /// Creates a new stopwatch which reads time from `source`, with the current time set to 0.
+    pub fn with_source(source: S) -> Self {
+        Self::with_source_and_speed(source, 1.0)
+    }
+
+    /// Like `with_source`, but returns an error instead of panicking if `source` can't be read.
+    pub fn try_with_source(source: S) -> Result<Self, StopwatchError> {
+        Self::try_with_source_and_speed(source, 1.0)
+    }
+
+    /// Like `with_speed`, but reads time from `source`.
+    ///
+    /// Panics if `speed` is NaN or infinite, or if `source` can't be read.
+    pub fn with_source_and_speed(source: S, speed: f64) -> Self {
+        Self::try_with_source_and_speed(source, speed).unwrap()
+    }
+
+    /// Like `with_source_and_speed`, but returns an error instead of panicking.
+    pub fn try_with_source_and_speed(source: S, speed: f64) -> Result<Self, StopwatchError> {
+        check_speed(speed)?;
+        let cur_time = source.try_now()?;
+        Ok(Self { elapsed: 0, running_since: Some(cur_time), speed, ramp: None, source })
+    }
+
+    /// Returns the time source this stopwatch reads from.
+    pub fn source(&self) -> &S {
+        &self.source
     }
 
     /// Returns whether the stopwatch is paused.
     pub fn paused(&self) -> bool {
-        self.paused_at.is_some()
+        self.running_since.is_none()
     }
 
     /// Pauses the stopwatch. If the stopwatch was already paused, this does nothing.
     pub fn pause(&mut self) {
-        self.paused_at = Some(self.get_end_time());
+        self.rebase();
+        self.running_since = None;
     }
 
     /// Unpauses the stopwatch. If the stopwatch was already unpaused, this does nothing.
     pub fn unpause(&mut self) {
         if self.paused() {
-            self.start_time = Self::get_raw_time() - self.get_time();
-            self.paused_at = None;
+            self.running_since = Some(self.source.now());
         }
     }
 
@@ -55,17 +174,89 @@ impl Stopwatch {
         }
     }
 
+    /// Returns the stopwatch's speed. During a speed ramp, this is the current point on the ramp.
+    pub fn speed(&self) -> f64 {
+        match (&self.ramp, self.running_since) {
+            (None, _) => self.speed,
+            (Some(ramp), None) => ramp.speed_at(self.speed, ramp.progress),
+            (Some(ramp), Some(running_since)) => {
+                let progress = ramp.progress + (self.source.now() - running_since);
+                ramp.speed_at(self.speed, progress)
+            }
+        }
+    }
+
+    /// Changes the stopwatch's speed. The current time is unaffected; only the rate at which it
+    /// advances from now on changes. Works whether or not the stopwatch is paused, and cancels
+    /// any speed ramp in progress.
+    ///
+    /// A speed of 0 freezes the stopwatch without pausing it, and a negative speed makes it run
+    /// backwards. Panics if `speed` is NaN or infinite.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.try_set_speed(speed).unwrap();
+    }
+
+    /// Like `set_speed`, but returns an error instead of panicking if `speed` is invalid. The
+    /// stopwatch is left unchanged if an error is returned.
+    pub fn try_set_speed(&mut self, speed: f64) -> Result<(), StopwatchError> {
+        check_speed(speed)?;
+        self.rebase();
+        self.speed = speed;
+        self.ramp = None;
+        Ok(())
+    }
+
+    /// Gradually changes the stopwatch's speed to `target` over `duration` seconds of real time,
+    /// following the given easing curve. The current time is unaffected.
+    ///
+    /// The ramp starts from the current speed and only progresses while the stopwatch is running.
+    /// Calling `set_speed` or starting another ramp replaces it. Ramps may cross zero, so a
+    /// stopwatch can smoothly slow to a stop and start rewinding.
+    ///
+    /// Panics if `target` is NaN or infinite.
+    pub fn ramp_speed(&mut self, target: f64, duration: f64, easing: Easing) {
+        self.try_ramp_speed(target, duration, easing).unwrap();
+    }
+
+    /// Like `ramp_speed`, but returns an error instead of panicking if `target` is invalid. The
+    /// stopwatch is left unchanged if an error is returned.
+    pub fn try_ramp_speed(
+        &mut self,
+        target: f64,
+        duration: f64,
+        easing: Easing,
+    ) -> Result<(), StopwatchError> {
+        check_speed(target)?;
+        let duration = secs_to_nanos(duration);
+        if duration <= 0 {
+            self.try_set_speed(target)
+        } else {
+            self.rebase();
+            self.speed = self.speed();
+            self.ramp = Some(SpeedRamp { target, easing, duration, progress: 0 });
+            Ok(())
+        }
+    }
+
+    /// Returns whether a speed ramp is in progress.
+    pub fn ramping(&self) -> bool {
+        match (&self.ramp, self.running_since) {
+            (None, _) => false,
+            (Some(ramp), None) => ramp.progress < ramp.duration,
+            (Some(ramp), Some(running_since)) => {
+                ramp.progress.saturating_add(self.source.now() - running_since) < ramp.duration
+            }
+        }
+    }
+
     /// Gets the current time.
     pub fn get_time(&self) -> f64 {
-        (self.get_end_time() - self.start_time) * self.speed
+        nanos_to_secs(self.get_nanos())
     }
 
     /// Sets the current time.
     pub fn set_time(&mut self, cur_time: f64) {
-        self.start_time = self.get_end_time() - cur_time / self.speed;
-        if self.paused() {
-            self.paused_at = Some(self.start_time);
-        }
+        self.set_nanos(secs_to_nanos(cur_time));
     }
 
     /// Resets the stopwatch to zero.
@@ -75,52 +266,403 @@ impl Stopwatch {
 
     /// Advances the stopwatch by `time_diff`.
     pub fn add_time(&mut self, time_diff: f64) {
-        self.start_time -= time_diff / self.speed;
-        match self.paused_at {
-            None => (),
-            Some(paused_at) => self.paused_at = Some(paused_at + time_diff),
+        self.elapsed = self.elapsed.saturating_add(secs_to_nanos(time_diff));
+    }
+
+    /// Gets the current time as a `Duration`. Returns zero if the current time is negative.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.get_nanos().max(0) as u64)
+    }
+
+    /// Sets the current time from a `Duration`. Durations too long to fit in an `i64` of
+    /// nanoseconds (about 292 years) are clamped to that limit.
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.set_nanos(duration_to_nanos(elapsed));
+    }
+
+    /// Advances the stopwatch by `duration`, saturating instead of overflowing.
+    pub fn add_duration(&mut self, duration: Duration) {
+        self.elapsed = self.elapsed.saturating_add(duration_to_nanos(duration));
+    }
+
+    /// Like `sleep_until`, but takes the time as a `Duration` and returns the overshoot as a
+    /// `Duration`.
+    ///
+    /// Panics if the stopwatch is paused or will never reach the given time.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn sleep_until_duration(&self, time: Duration) -> Duration {
+        self.try_sleep_until_duration(time).unwrap()
+    }
+
+    /// Like `sleep_until_duration`, but returns an error instead of panicking.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn try_sleep_until_duration(&self, time: Duration) -> Result<Duration, StopwatchError> {
+        let overshoot = self.try_sleep_until_nanos(duration_to_nanos(time))?;
+        Ok(Duration::from_nanos(overshoot as u64))
+    }
+
+    /// Sleeps until this stopwatch reaches the given time, taking its speed into account. May
+    /// sleep for slightly longer than requested (the same behavior as `std::thread::sleep`), so in
+    /// practice most games should use vsync or a `FrameLimiter` instead of using this to maintain a
+    /// certain frame rate.
+    ///
+    /// If the given time has already passed, this returns immediately. If the stopwatch is running
+    /// backwards and the given time is behind it, this waits for it to rewind to that time.
+    /// Returns how far past the given time the stopwatch went, in stopwatch time, so callers can
+    /// compensate for oversleeping.
+    ///
+    /// Panics if the stopwatch is paused or will never reach the given time.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn sleep_until(&self, time: f64) -> f64 {
+        self.try_sleep_until(time).unwrap()
+    }
+
+    /// Like `sleep_until`, but returns an error instead of panicking. The error is returned before
+    /// sleeping if the stopwatch is paused, or if `time` is ahead of it and it's frozen or running
+    /// backwards, but may also be returned partway through if its speed changes during a ramp.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn try_sleep_until(&self, time: f64) -> Result<f64, StopwatchError> {
+        Ok(nanos_to_secs(self.try_sleep_until_nanos(secs_to_nanos(time))?))
+    }
+
+    /// Sleeps until `secs` of stopwatch time have passed, and returns the overshoot like
+    /// `sleep_until`. If the stopwatch is running backwards, `secs` should be negative.
+    ///
+    /// Panics if the stopwatch is paused or will never advance by `secs`.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn sleep_for(&self, secs: f64) -> f64 {
+        self.try_sleep_for(secs).unwrap()
+    }
+
+    /// Like `sleep_for`, but returns an error instead of panicking.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn try_sleep_for(&self, secs: f64) -> Result<f64, StopwatchError> {
+        self.try_sleep_until(self.get_time() + secs)
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn try_sleep_until_nanos(&self, target: i64) -> Result<i64, StopwatchError> {
+        if self.paused() {
+            return Err(StopwatchError::Paused);
+        }
+        let direction = self.direction_to(target);
+        loop {
+            let remaining = target.saturating_sub(self.get_nanos()).saturating_mul(direction);
+            if remaining <= 0 {
+                return Ok(remaining.saturating_neg());
+            }
+            let wait = self.wait_nanos(remaining, direction).ok_or(StopwatchError::Unreachable)?;
+            self.source.sleep(Duration::from_nanos(wait as u64));
+        }
+    }
+
+    /// Returns which way the stopwatch has to move to reach `target`: -1 if `target` is behind it
+    /// and it's running backwards, and 1 otherwise. A target behind a stopwatch which isn't
+    /// running backwards has already been passed.
+    pub(crate) fn direction_to(&self, target: i64) -> i64 {
+        let mut speed = self.speed();
+        if speed == 0.0 {
+            speed = self.ramp.map_or(0.0, |ramp| ramp.target);
+        }
+        if speed < 0.0 && target < self.get_nanos() {
+            -1
+        } else {
+            1
         }
     }
 
-    /// Sleeps until this stopwatch reaches the given time. May sleep for slightly longer than
-    /// requested (the same behavior as `std::thread::sleep`), so in practice most games should
-    /// use vsync or similar mechanisms instead of using this to maintain a certain frame rate.
+    /// Returns how many nanoseconds of the source's time it's safe to wait for the stopwatch to
+    /// move `remaining` nanoseconds in `direction`, or `None` if it isn't moving that way.
     ///
-    /// Panics if the stopwatch is paused.
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn sleep_until(&self, time: f64) {
-        assert!(!self.paused());
-        let time_diff = time / self.speed - self.get_time();
-        Self::sleep(time_diff);
+    /// Waiting for the remaining time at the fastest speed the stopwatch will reach soon can't
+    /// overshoot, even during a speed ramp. If the stopwatch speeds up, that's only a lower bound,
+    /// so callers should keep waiting until the target is reached.
+    pub(crate) fn wait_nanos(&self, remaining: i64, direction: i64) -> Option<i64> {
+        let mut rate = self.speed() * direction as f64;
+        if let Some(ramp) = &self.ramp {
+            rate = rate.max(ramp.target * direction as f64);
+        }
+        if rate <= 0.0 {
+            None
+        } else {
+            Some((remaining as f64 / rate).ceil().max(1.0) as i64)
+        }
     }
 
-    fn get_end_time(&self) -> f64 {
-        match self.paused_at {
-            None => Self::get_raw_time(),
-            Some(paused_at) => paused_at,
+    pub(crate) fn get_nanos(&self) -> i64 {
+        match self.running_since {
+            None => self.elapsed,
+            Some(running_since) => {
+                self.elapsed.saturating_add(self.scale(self.source.now() - running_since))
+            }
         }
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn get_raw_time() -> f64 {
-        window().unwrap().performance().unwrap().now() / 1000.0
+    fn set_nanos(&mut self, nanos: i64) {
+        self.rebase();
+        self.elapsed = nanos;
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn get_raw_time() -> f64 {
-        (time::OffsetDateTime::now_utc() - time::OffsetDateTime::UNIX_EPOCH).as_seconds_f64()
+    /// Converts nanoseconds of the source's time since `running_since` to nanoseconds of
+    /// stopwatch time.
+    fn scale(&self, raw_elapsed: i64) -> i64 {
+        match &self.ramp {
+            None => (raw_elapsed as f64 * self.speed).round() as i64,
+            Some(ramp) => ramp.integrate(self.speed, raw_elapsed).round() as i64,
+        }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn sleep(time_diff: f64) {
-        if time_diff > 0.0 {
-            std::thread::sleep(std::time::Duration::from_secs_f64(time_diff));
+    /// Folds the time since `running_since` into `elapsed`, so the stopwatch's state can be changed
+    /// without affecting the time it has already accumulated.
+    fn rebase(&mut self) {
+        if let Some(running_since) = self.running_since {
+            let now = self.source.now();
+            let raw_elapsed = now - running_since;
+            self.elapsed = self.elapsed.saturating_add(self.scale(raw_elapsed));
+            self.running_since = Some(now);
+            if let Some(ramp) = &mut self.ramp {
+                ramp.progress = ramp.progress.saturating_add(raw_elapsed);
+                if ramp.progress >= ramp.duration {
+                    self.speed = ramp.target;
+                    self.ramp = None;
+                }
+            }
         }
     }
 }
 
+fn check_speed(speed: f64) -> Result<(), StopwatchError> {
+    if speed.is_finite() {
+        Ok(())
+    } else {
+        Err(StopwatchError::InvalidSpeed(speed))
+    }
+}
+
 impl Default for Stopwatch {
     fn default() -> Self {
         Self::new()
     }
 }
+
+impl<S: TimeSource> AddAssign<Duration> for Stopwatch<S> {
+    fn add_assign(&mut self, duration: Duration) {
+        self.add_duration(duration);
+    }
+}
+
+impl<S: TimeSource> SubAssign<Duration> for Stopwatch<S> {
+    fn sub_assign(&mut self, duration: Duration) {
+        self.elapsed = self.elapsed.saturating_sub(duration_to_nanos(duration));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct TestClock(Rc<Cell<i64>>);
+
+    impl TimeSource for TestClock {
+        fn now(&self) -> i64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn custom_source() {
+        let clock = TestClock::default();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        clock.0.set(1_500_000_000);
+        assert_eq!(stopwatch.get_time(), 1.5);
+        stopwatch.pause();
+        clock.0.set(2_500_000_000);
+        assert_eq!(stopwatch.get_time(), 1.5);
+        stopwatch.unpause();
+        clock.0.set(3_500_000_000);
+        assert_eq!(stopwatch.get_time(), 2.5);
+        stopwatch.set_time(10.0);
+        stopwatch.add_time(1.0);
+        assert_eq!(stopwatch.get_time(), 11.0);
+    }
+
+    #[test]
+    fn precision() {
+        let clock = ManualClock::new();
+        clock.set_time(1.7e9);
+        let stopwatch = Stopwatch::with_source(clock.clone());
+        clock.advance(1e-9);
+        assert_eq!(stopwatch.get_nanos(), 1);
+    }
+
+    #[test]
+    fn saturation() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        stopwatch.add_time(1e10);
+        stopwatch.add_time(1.0);
+        assert_eq!(stopwatch.get_nanos(), i64::MAX);
+        clock.advance(1.0);
+        assert_eq!(stopwatch.get_nanos(), i64::MAX);
+    }
+
+    #[test]
+    fn set_speed() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        clock.advance(1.0);
+        stopwatch.set_speed(0.5);
+        assert_eq!(stopwatch.speed(), 0.5);
+        assert_eq!(stopwatch.get_time(), 1.0);
+        clock.advance(1.0);
+        assert_eq!(stopwatch.get_time(), 1.5);
+
+        stopwatch.pause();
+        stopwatch.set_speed(2.0);
+        clock.advance(1.0);
+        assert_eq!(stopwatch.get_time(), 1.5);
+        stopwatch.unpause();
+        clock.advance(1.0);
+        assert_eq!(stopwatch.get_time(), 3.5);
+    }
+
+    #[test]
+    fn ramp_integration() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            let clock = ManualClock::new();
+            let mut stopwatch = Stopwatch::with_source(clock.clone());
+            stopwatch.ramp_speed(0.2, 0.5, easing);
+            let mut sampled = stopwatch.clone();
+
+            // Integrate the speed numerically with the midpoint rule.
+            let steps = 100_000;
+            let expected: f64 = (0..steps)
+                .map(|i| {
+                    let t = (i as f64 + 0.5) / steps as f64;
+                    (1.0 + (0.2 - 1.0) * easing.apply(t)) * 0.5 / steps as f64
+                })
+                .sum();
+
+            // Rebasing partway through the ramp mustn't change the result.
+            for _ in 0..10 {
+                clock.advance(0.05);
+                sampled.pause();
+                sampled.unpause();
+            }
+            assert!((stopwatch.get_time() - expected).abs() < 1e-8, "{:?}", easing);
+            assert!((sampled.get_time() - expected).abs() < 1e-8, "{:?}", easing);
+
+            clock.advance(1.0);
+            assert!((stopwatch.get_time() - expected - 0.2).abs() < 1e-8, "{:?}", easing);
+            assert!(!stopwatch.ramping());
+            assert_eq!(stopwatch.speed(), 0.2);
+        }
+    }
+
+    #[test]
+    fn rewind_and_freeze() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        clock.advance(2.0);
+        stopwatch.set_speed(-1.0);
+        clock.advance(0.5);
+        assert_eq!(stopwatch.get_time(), 1.5);
+
+        stopwatch.set_speed(0.0);
+        clock.advance(3.0);
+        assert_eq!(stopwatch.get_time(), 1.5);
+        stopwatch.set_time(5.0);
+        stopwatch.add_time(1.0);
+        assert_eq!(stopwatch.get_time(), 6.0);
+
+        assert!(matches!(stopwatch.try_set_speed(f64::NAN), Err(StopwatchError::InvalidSpeed(_))));
+        assert_eq!(
+            stopwatch.try_ramp_speed(f64::INFINITY, 1.0, Easing::Linear),
+            Err(StopwatchError::InvalidSpeed(f64::INFINITY))
+        );
+        assert_eq!(stopwatch.speed(), 0.0);
+        assert!(Stopwatch::try_with_source_and_speed(clock, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn ramp_through_zero() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        stopwatch.ramp_speed(-1.0, 1.0, Easing::Linear);
+        clock.advance(1.0);
+        assert!(stopwatch.get_time().abs() < 1e-9);
+        clock.advance(1.0);
+        assert!((stopwatch.get_time() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn sleep_until() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source_and_speed(clock.clone(), 2.0);
+        assert_eq!(stopwatch.sleep_until(3.0), 0.0);
+        assert_eq!(clock.time(), 1.5);
+        assert_eq!(stopwatch.sleep_for(1.0), 0.0);
+        assert_eq!(clock.time(), 2.0);
+
+        // A time which has already passed returns the overshoot without sleeping.
+        assert_eq!(stopwatch.sleep_until(1.0), 3.0);
+        assert_eq!(clock.time(), 2.0);
+
+        stopwatch.set_speed(-1.0);
+        assert_eq!(stopwatch.sleep_until(1.0), 0.0);
+        assert_eq!(stopwatch.get_time(), 1.0);
+
+        stopwatch.set_speed(0.0);
+        assert_eq!(stopwatch.sleep_until(0.5), 0.5);
+    }
+
+    #[test]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn sleep_errors() {
+        let mut stopwatch = Stopwatch::try_with_source(ManualClock::new()).unwrap();
+        stopwatch.set_speed(-1.0);
+        assert_eq!(stopwatch.try_sleep_until(1.0), Err(StopwatchError::Unreachable));
+        stopwatch.set_speed(0.0);
+        assert_eq!(stopwatch.try_sleep_for(1.0), Err(StopwatchError::Unreachable));
+        stopwatch.pause();
+        assert_eq!(stopwatch.try_sleep_until(-1.0), Err(StopwatchError::Paused));
+        assert_eq!(
+            stopwatch.try_sleep_until_duration(Duration::ZERO),
+            Err(StopwatchError::Paused)
+        );
+    }
+
+    #[test]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn sleep_during_ramp() {
+        let mut stopwatch = Stopwatch::with_source(ManualClock::new());
+        stopwatch.ramp_speed(4.0, 1.0, Easing::EaseIn);
+        assert!(stopwatch.sleep_for(2.0) < 1e-8);
+        assert!((stopwatch.get_time() - 2.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn durations() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        clock.advance(1.0);
+        stopwatch += Duration::from_millis(500);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(1500));
+        stopwatch -= Duration::from_secs(2);
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+
+        stopwatch.set_elapsed(Duration::MAX);
+        clock.advance(1.0);
+        assert_eq!(stopwatch.elapsed(), Duration::from_nanos(i64::MAX as u64));
+        stopwatch.add_duration(Duration::MAX);
+        assert_eq!(stopwatch.elapsed(), Duration::from_nanos(i64::MAX as u64));
+        stopwatch.pause();
+        stopwatch -= Duration::MAX;
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::time_source::*;
+use crate::*;
+
+/// A system which is due to run, as reported by `Scheduler::update`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Due<'a> {
+    /// The name the system was registered with.
+    pub name: &'a str,
+    /// How many ticks the system owes this frame.
+    pub ticks: u32,
+    /// The length of each tick in stopwatch time.
+    pub period: f64,
+}
+
+/// Runs several systems at different fixed rates off the same `Stopwatch`.
+///
+/// For instance, physics might run at 120 Hz, gameplay at 30 Hz and AI at 5 Hz. Each frame,
+/// `update` reports which systems are due and how many ticks each owes. Giving heavy systems
+/// different phases staggers their ticks across frames so they don't all land on the same one.
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    systems: Vec<System>,
+    max_ticks: u32,
+}
+
+#[derive(Clone, Debug)]
+struct System {
+    name: String,
+    period: f64,
+    phase: f64,
+    /// The stopwatch time of the next tick, or `None` if the system hasn't been updated yet.
+    next_tick: Option<f64>,
+    due: u32,
+}
+
+impl Scheduler {
+    /// Creates a scheduler with no systems, which reports at most 8 ticks per system per frame.
+    pub fn new() -> Self {
+        Self { systems: vec![], max_ticks: 8 }
+    }
+
+    /// Sets the maximum number of ticks reported for each system per frame. Ticks beyond that are
+    /// dropped, so that systems which can't keep up don't fall further and further behind.
+    pub fn set_max_ticks(&mut self, max_ticks: u32) {
+        self.max_ticks = max_ticks;
+    }
+
+    /// Registers a system which runs `hz` times per second of stopwatch time. Its first tick is
+    /// due on the next `update`.
+    pub fn add_rate(&mut self, name: impl Into<String>, hz: f64) {
+        self.add_rate_with_phase(name, hz, 0.0);
+    }
+
+    /// Like `add_rate`, but delays the system's ticks by `phase` periods, which should be between
+    /// 0 and 1. For instance, two 5 Hz systems with phases 0 and 0.5 tick on alternate 10 Hz
+    /// boundaries.
+    ///
+    /// If a system with this name already exists, it's replaced.
+    pub fn add_rate_with_phase(&mut self, name: impl Into<String>, hz: f64, phase: f64) {
+        assert!(hz > 0.0, "the rate must be positive");
+        assert!((0.0..=1.0).contains(&phase), "the phase must be between 0 and 1");
+        let name = name.into();
+        self.remove(&name);
+        self.systems.push(System { name, period: 1.0 / hz, phase, next_tick: None, due: 0 });
+    }
+
+    /// Unregisters the system with the given name. Returns whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.systems.len();
+        self.systems.retain(|system| system.name != name);
+        self.systems.len() != len
+    }
+
+    /// Works out how many ticks each system owes based on the stopwatch's current time, and
+    /// returns the systems which are due, in the order they were registered.
+    ///
+    /// If the stopwatch goes backwards, each system's schedule is brought back to within a period
+    /// of the new time, so systems keep ticking once it runs forwards again.
+    pub fn update<S: TimeSource>(&mut self, stopwatch: &Stopwatch<S>) -> Vec<Due<'_>> {
+        let time = stopwatch.get_time();
+        for system in &mut self.systems {
+            let mut next_tick =
+                *system.next_tick.get_or_insert(time + system.phase * system.period);
+            if next_tick - time > system.period {
+                next_tick -= system.period * ((next_tick - time) / system.period).floor();
+                system.next_tick = Some(next_tick);
+            }
+            system.due = 0;
+            if time >= next_tick {
+                let owed = ((time - next_tick) / system.period).floor() + 1.0;
+                system.next_tick = Some(next_tick + owed * system.period);
+                system.due = owed.min(self.max_ticks as f64) as u32;
+            }
+        }
+        self.systems
+            .iter()
+            .filter(|system| system.due > 0)
+            .map(|system| Due { name: &system.name, ticks: system.due, period: system.period })
+            .collect()
+    }
+
+    /// Returns how many ticks the system with the given name owed as of the last `update`, or 0 if
+    /// there's no such system.
+    pub fn ticks(&self, name: &str) -> u32 {
+        self.systems.iter().find(|system| system.name == name).map_or(0, |system| system.due)
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_and_phases() {
+        let clock = ManualClock::new();
+        let stopwatch = Stopwatch::with_source(clock.clone());
+        let mut scheduler = Scheduler::new();
+        scheduler.add_rate("physics", 8.0);
+        scheduler.add_rate_with_phase("ai", 2.0, 0.5);
+        let due = scheduler.update(&stopwatch);
+        assert_eq!(due, [Due { name: "physics", ticks: 1, period: 0.125 }]);
+
+        clock.advance(0.25);
+        let due = scheduler.update(&stopwatch);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].ticks, 2);
+        assert_eq!(scheduler.ticks("ai"), 1);
+
+        clock.advance(0.25);
+        scheduler.update(&stopwatch);
+        assert_eq!(scheduler.ticks("ai"), 0);
+        assert!(scheduler.remove("ai"));
+        assert!(!scheduler.remove("ai"));
+    }
+
+    #[test]
+    fn max_ticks() {
+        let clock = ManualClock::new();
+        let stopwatch = Stopwatch::with_source(clock.clone());
+        let mut scheduler = Scheduler::new();
+        scheduler.add_rate("physics", 8.0);
+        scheduler.update(&stopwatch);
+        clock.advance(10.0);
+        assert_eq!(scheduler.ticks("physics"), 1);
+        scheduler.update(&stopwatch);
+        assert_eq!(scheduler.ticks("physics"), 8);
+
+        // The dropped ticks don't carry over to the next update.
+        clock.advance(0.125);
+        scheduler.update(&stopwatch);
+        assert_eq!(scheduler.ticks("physics"), 1);
+    }
+    #[test]
+    fn rewind() {
+        let clock = ManualClock::new();
+        let mut stopwatch = Stopwatch::with_source(clock.clone());
+        let mut scheduler = Scheduler::new();
+        scheduler.add_rate("physics", 4.0);
+        clock.advance(10.0);
+        scheduler.update(&stopwatch);
+
+        stopwatch.set_time(2.125);
+        assert!(scheduler.update(&stopwatch).is_empty());
+        clock.advance(0.25);
+        assert_eq!(scheduler.ticks("physics"), 0);
+        scheduler.update(&stopwatch);
+        assert_eq!(scheduler.ticks("physics"), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "the phase must be between 0 and 1")]
+    fn invalid_phase() {
+        Scheduler::new().add_rate_with_phase("ai", 5.0, 1.5);
+    }
+}
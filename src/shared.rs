@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+#[cfg(not(target_os = "wasi"))]
+use std::future::Future;
+#[cfg(not(target_os = "wasi"))]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Waker;
+#[cfg(not(target_os = "wasi"))]
+use std::task::{Context, Poll};
+#[cfg(not(target_os = "wasi"))]
+use std::time::Duration;
+
+use crate::easing::*;
+use crate::time_source::*;
+use crate::*;
+
+/// A `Stopwatch` which can be shared between tasks and waited on asynchronously.
+///
+/// Clones refer to the same stopwatch. Changing it through this handle (pausing, changing its
+/// speed or time, and so on) wakes any futures waiting on it, so they can recompute their
+/// deadlines.
+pub struct SharedStopwatch<S: TimeSource = DefaultClock> {
+    inner: Arc<Mutex<Inner<S>>>,
+}
+
+struct Inner<S: TimeSource> {
+    stopwatch: Stopwatch<S>,
+    /// The wakers of the futures waiting on the stopwatch, keyed by `SleepUntil::key`.
+    wakers: HashMap<u64, Waker>,
+    #[cfg(not(target_os = "wasi"))]
+    next_key: u64,
+}
+
+impl SharedStopwatch {
+    /// Creates a new shared stopwatch with the current time set to 0.
+    pub fn new() -> Self {
+        Self::from_stopwatch(Stopwatch::new())
+    }
+}
+
+impl<S: TimeSource> SharedStopwatch<S> {
+    /// Wraps an existing stopwatch.
+    pub fn from_stopwatch(stopwatch: Stopwatch<S>) -> Self {
+        let inner = Inner {
+            stopwatch,
+            wakers: HashMap::new(),
+            #[cfg(not(target_os = "wasi"))]
+            next_key: 0,
+        };
+        Self { inner: Arc::new(Mutex::new(inner)) }
+    }
+
+    /// Calls `f` with the underlying stopwatch.
+    pub fn with<R>(&self, f: impl FnOnce(&Stopwatch<S>) -> R) -> R {
+        f(&self.lock().stopwatch)
+    }
+
+    /// Calls `f` with the underlying stopwatch mutably, then wakes any futures waiting on it.
+    pub fn update<R>(&self, f: impl FnOnce(&mut Stopwatch<S>) -> R) -> R {
+        let mut inner = self.lock();
+        let result = f(&mut inner.stopwatch);
+        let wakers = std::mem::take(&mut inner.wakers);
+        drop(inner);
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+        result
+    }
+
+    /// Returns a copy of the underlying stopwatch.
+    pub fn snapshot(&self) -> Stopwatch<S>
+    where
+        S: Clone,
+    {
+        self.with(|stopwatch| stopwatch.clone())
+    }
+
+    /// Gets the current time.
+    pub fn get_time(&self) -> f64 {
+        self.with(|stopwatch| stopwatch.get_time())
+    }
+
+    /// Sets the current time.
+    pub fn set_time(&self, cur_time: f64) {
+        self.update(|stopwatch| stopwatch.set_time(cur_time));
+    }
+
+    /// Advances the stopwatch by `time_diff`.
+    pub fn add_time(&self, time_diff: f64) {
+        self.update(|stopwatch| stopwatch.add_time(time_diff));
+    }
+
+    /// Resets the stopwatch to zero.
+    pub fn reset(&self) {
+        self.update(|stopwatch| stopwatch.reset());
+    }
+
+    /// Returns whether the stopwatch is paused.
+    pub fn paused(&self) -> bool {
+        self.with(|stopwatch| stopwatch.paused())
+    }
+
+    /// Pauses the stopwatch. If the stopwatch was already paused, this does nothing.
+    pub fn pause(&self) {
+        self.update(|stopwatch| stopwatch.pause());
+    }
+
+    /// Unpauses the stopwatch. If the stopwatch was already unpaused, this does nothing.
+    pub fn unpause(&self) {
+        self.update(|stopwatch| stopwatch.unpause());
+    }
+
+    /// Toggles whether the stopwatch is paused.
+    pub fn toggle_pause(&self) {
+        self.update(|stopwatch| stopwatch.toggle_pause());
+    }
+
+    /// Returns the stopwatch's speed.
+    pub fn speed(&self) -> f64 {
+        self.with(|stopwatch| stopwatch.speed())
+    }
+
+    /// Changes the stopwatch's speed. See `Stopwatch::set_speed`.
+    pub fn set_speed(&self, speed: f64) {
+        self.update(|stopwatch| stopwatch.set_speed(speed));
+    }
+
+    /// Like `set_speed`, but returns an error instead of panicking if `speed` is invalid.
+    pub fn try_set_speed(&self, speed: f64) -> Result<(), StopwatchError> {
+        self.update(|stopwatch| stopwatch.try_set_speed(speed))
+    }
+
+    /// Gradually changes the stopwatch's speed. See `Stopwatch::ramp_speed`.
+    pub fn ramp_speed(&self, target: f64, duration: f64, easing: Easing) {
+        self.update(|stopwatch| stopwatch.ramp_speed(target, duration, easing));
+    }
+
+    /// Returns a future which completes once the stopwatch reaches the given time, and resolves to
+    /// how far past that time it went, like `Stopwatch::sleep_until`.
+    ///
+    /// The future respects the stopwatch's speed, and while the stopwatch is paused, frozen or
+    /// running away from `time`, it waits for that to change rather than failing. Like
+    /// `Stopwatch::sleep_until`, it completes immediately if `time` has already passed, and only
+    /// waits for the stopwatch to rewind if it's running backwards when the future is created.
+    /// It works with any async runtime.
+    ///
+    /// Not available on WASI, which has no way to wake a task after a delay without threads.
+    #[cfg(not(target_os = "wasi"))]
+    pub fn sleep_until_async(&self, time: f64) -> SleepUntil<S> {
+        let target = secs_to_nanos(time);
+        let direction = self.with(|stopwatch| stopwatch.direction_to(target));
+        SleepUntil { stopwatch: self.clone(), target, direction, key: None, scheduled: None }
+    }
+
+    /// Like `sleep_until_async`, but only completes by running forwards to `time`, so it also waits
+    /// while the stopwatch runs backwards.
+    #[cfg(not(target_os = "wasi"))]
+    pub(crate) fn sleep_until_forwards(&self, time: f64) -> SleepUntil<S> {
+        let target = secs_to_nanos(time);
+        SleepUntil { stopwatch: self.clone(), target, direction: 1, key: None, scheduled: None }
+    }
+
+    /// Like `sleep_until_async`, but waits until `secs` of stopwatch time have passed.
+    #[cfg(not(target_os = "wasi"))]
+    pub fn sleep_for_async(&self, secs: f64) -> SleepUntil<S> {
+        self.sleep_until_async(self.get_time() + secs)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<S>> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl<S: TimeSource> Clone for SharedStopwatch<S> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl Default for SharedStopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A future which completes once a `SharedStopwatch` reaches a given time. Created by
+/// `SharedStopwatch::sleep_until_async`.
+#[cfg(not(target_os = "wasi"))]
+pub struct SleepUntil<S: TimeSource = DefaultClock> {
+    stopwatch: SharedStopwatch<S>,
+    target: i64,
+    direction: i64,
+    /// The key of this future's waker in the stopwatch's wakers, if it has registered one.
+    key: Option<u64>,
+    /// The source time at which a timer is already scheduled to wake this future.
+    scheduled: Option<i64>,
+}
+
+#[cfg(not(target_os = "wasi"))]
+impl<S: TimeSource> SleepUntil<S> {
+    /// Polls the sleep, returning the overshoot in nanoseconds once it's complete.
+    pub(crate) fn poll_nanos(&mut self, cx: &mut Context) -> Poll<i64> {
+        let mut inner = self.stopwatch.lock();
+        let remaining =
+            self.target.saturating_sub(inner.stopwatch.get_nanos()).saturating_mul(self.direction);
+        if remaining <= 0 {
+            if let Some(key) = self.key.take() {
+                inner.wakers.remove(&key);
+            }
+            return Poll::Ready(remaining.saturating_neg());
+        }
+
+        let key = *self.key.get_or_insert_with(|| {
+            inner.next_key += 1;
+            inner.next_key
+        });
+        if !inner.wakers.get(&key).is_some_and(|waker| waker.will_wake(cx.waker())) {
+            inner.wakers.insert(key, cx.waker().clone());
+        }
+        let stopwatch = &inner.stopwatch;
+        if !stopwatch.paused() {
+            if let Some(wait) = stopwatch.wait_nanos(remaining, self.direction) {
+                let now = stopwatch.source().now();
+                let deadline = now.saturating_add(wait);
+                let already_scheduled = self
+                    .scheduled
+                    .is_some_and(|scheduled| scheduled > now && scheduled <= deadline);
+                if !already_scheduled {
+                    self.scheduled = Some(deadline);
+                    timer::wake_after(Duration::from_nanos(wait as u64), cx.waker().clone());
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+impl<S: TimeSource> Future for SleepUntil<S> {
+    type Output = f64;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<f64> {
+        self.get_mut().poll_nanos(cx).map(nanos_to_secs)
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+impl<S: TimeSource> Drop for SleepUntil<S> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            if let Ok(mut inner) = self.stopwatch.inner.lock() {
+                inner.wakers.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(target_os = "wasi")))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    use super::*;
+
+    fn poll(sleep: &mut SleepUntil<ManualClock>) -> Poll<f64> {
+        Pin::new(sleep).poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn shared() -> (ManualClock, SharedStopwatch<ManualClock>) {
+        let clock = ManualClock::new();
+        (clock.clone(), SharedStopwatch::from_stopwatch(Stopwatch::with_source(clock)))
+    }
+
+    #[test]
+    fn sleep_with_speed_and_pause() {
+        let (clock, stopwatch) = shared();
+        stopwatch.set_speed(2.0);
+        let mut sleep = stopwatch.sleep_until_async(1.0);
+        assert_eq!(poll(&mut sleep), Poll::Pending);
+        stopwatch.pause();
+        clock.advance(1.0);
+        assert_eq!(poll(&mut sleep), Poll::Pending);
+        stopwatch.unpause();
+        clock.advance(0.625);
+        assert_eq!(poll(&mut sleep), Poll::Ready(0.25));
+
+        // A time which has already passed completes immediately.
+        assert_eq!(poll(&mut stopwatch.sleep_until_async(0.5)), Poll::Ready(0.75));
+    }
+
+    #[test]
+    fn sleep_while_rewinding() {
+        let (clock, stopwatch) = shared();
+        stopwatch.set_speed(-1.0);
+        let mut ahead = stopwatch.sleep_until_async(5.0);
+        let mut behind = stopwatch.sleep_until_async(-2.0);
+        assert_eq!(poll(&mut ahead), Poll::Pending);
+        assert_eq!(poll(&mut behind), Poll::Pending);
+        clock.advance(2.0);
+        assert_eq!(poll(&mut ahead), Poll::Pending);
+        assert_eq!(poll(&mut behind), Poll::Ready(0.0));
+
+        stopwatch.set_speed(1.0);
+        clock.advance(7.0);
+        assert_eq!(poll(&mut ahead), Poll::Ready(0.0));
+    }
+
+    #[test]
+    fn wakers() {
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let (clock, stopwatch) = shared();
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut sleep = stopwatch.sleep_for_async(1.0);
+        let mut dropped = stopwatch.sleep_for_async(1.0);
+        assert!(Pin::new(&mut sleep).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut sleep).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut dropped).poll(&mut cx).is_pending());
+        assert_eq!(stopwatch.lock().wakers.len(), 2);
+        drop(dropped);
+        assert_eq!(stopwatch.lock().wakers.len(), 1);
+
+        stopwatch.pause();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        stopwatch.unpause();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        clock.advance(1.0);
+        assert!(Pin::new(&mut sleep).poll(&mut cx).is_ready());
+        assert!(stopwatch.lock().wakers.is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+//! Clocks that a `Stopwatch` can read time from.
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::sync::OnceLock;
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+use crate::StopwatchError;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use js_sys::{Array, Date, Function};
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_sys::Performance;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use crate::js::*;
+
+/// A source of time for a `Stopwatch`.
+///
+/// Implement this to drive a stopwatch from a custom clock.
+pub trait TimeSource {
+    /// Returns the current time in nanoseconds. Only differences between values are meaningful,
+    /// so implementations can count from any origin, such as process start or the Unix epoch.
+    fn now(&self) -> i64;
+
+    /// Like `now`, but returns an error if the clock can't be read. Implementations which can
+    /// fail should override this and make `now` panic on failure.
+    fn try_now(&self) -> Result<i64, StopwatchError> {
+        Ok(self.now())
+    }
+
+    /// Blocks the current thread for the given duration.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    /// Busy-waits until `now` returns at least `deadline`.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn spin_until(&self, deadline: i64) {
+        while self.now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Reads time from `performance.now()` in the current JavaScript global scope, so it works in
+/// windows, Web Workers, worklets and Node.js alike.
+///
+/// If `performance` isn't available, it falls back to Node's `process.hrtime()`, and then to
+/// `Date.now()`, which only has millisecond resolution and follows changes to the system clock.
+/// The clocks count from different origins, so if `process.hrtime()` exists but throws, `try_now`
+/// returns `StopwatchError::ClockUnavailable` and `now` panics rather than switching to
+/// `Date.now()`.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PerformanceClock;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl TimeSource for PerformanceClock {
+    fn now(&self) -> i64 {
+        self.try_now().expect("process.hrtime() threw an exception")
+    }
+
+    fn try_now(&self) -> Result<i64, StopwatchError> {
+        // Looking up the clock crosses the JS boundary several times, so it's only done once per
+        // thread.
+        thread_local! {
+            static BACKEND: JsClock = JsClock::find();
+        }
+        BACKEND.with(JsClock::try_now)
+    }
+}
+
+/// The clock `PerformanceClock` found in the current JavaScript global scope.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+enum JsClock {
+    Performance(Performance),
+    Hrtime { process: JsValue, hrtime: Function },
+    Date,
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl JsClock {
+    fn find() -> Self {
+        let global = js_sys::global();
+        if let Some(performance) = property(&global, "performance") {
+            if property(&performance, "now").is_some() {
+                return JsClock::Performance(performance.unchecked_into());
+            }
+        }
+        if let Some(process) = property(&global, "process") {
+            if let Some(hrtime) = property(&process, "hrtime") {
+                return JsClock::Hrtime { process, hrtime: hrtime.unchecked_into() };
+            }
+        }
+        JsClock::Date
+    }
+
+    fn try_now(&self) -> Result<i64, StopwatchError> {
+        match self {
+            JsClock::Performance(performance) => Ok((performance.now() * 1_000_000.0) as i64),
+            JsClock::Hrtime { process, hrtime } => {
+                let time: Array = hrtime
+                    .call0(process)
+                    .map_err(|_| StopwatchError::ClockUnavailable)?
+                    .unchecked_into();
+                let secs = time.get(0).as_f64().unwrap_or(0.0) as i64;
+                let nanos = time.get(1).as_f64().unwrap_or(0.0) as i64;
+                Ok(secs * 1_000_000_000 + nanos)
+            }
+            JsClock::Date => Ok((Date::now() * 1_000_000.0) as i64),
+        }
+    }
+}
+
+/// Reads time from `std::time::Instant`, which never goes backwards and isn't affected by changes
+/// to the system clock.
+///
+/// This is the default on desktop and on WASI, where `Instant` is backed by WASI's monotonic
+/// `clock_time_get`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MonotonicClock;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl TimeSource for MonotonicClock {
+    fn now(&self) -> i64 {
+        static ORIGIN: OnceLock<Instant> = OnceLock::new();
+        ORIGIN.get_or_init(Instant::now).elapsed().as_nanos() as i64
+    }
+}
+
+/// Reads time from the system's UTC wall clock, counting from the Unix epoch.
+///
+/// Unlike `MonotonicClock`, this follows any adjustments to the system clock, so a stopwatch
+/// using it can jump forwards or backwards. Only use it if the stopwatch needs to stay in sync
+/// with the wall clock.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UtcClock;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl TimeSource for UtcClock {
+    fn now(&self) -> i64 {
+        (time::OffsetDateTime::now_utc() - time::OffsetDateTime::UNIX_EPOCH).whole_nanoseconds()
+            as i64
+    }
+}
+
+/// A clock which only advances when told to, for deterministic tests.
+///
+/// Clones share the same underlying time, so a test can keep one handle and give a clone to a
+/// `Stopwatch`. Sleeping or spinning on a `ManualClock` advances it by the requested amount
+/// instead of blocking.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    nanos: Arc<AtomicI64>,
+}
+
+impl ManualClock {
+    /// Creates a new clock with the time set to 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the clock's current time in seconds.
+    pub fn time(&self) -> f64 {
+        nanos_to_secs(self.nanos.load(Ordering::SeqCst))
+    }
+
+    /// Sets the clock's current time in seconds.
+    pub fn set_time(&self, time: f64) {
+        self.nanos.store(secs_to_nanos(time), Ordering::SeqCst);
+    }
+
+    /// Advances the clock by `secs` seconds.
+    pub fn advance(&self, secs: f64) {
+        self.nanos.fetch_add(secs_to_nanos(secs), Ordering::SeqCst);
+    }
+}
+
+impl TimeSource for ManualClock {
+    fn now(&self) -> i64 {
+        self.nanos.load(Ordering::SeqCst)
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn sleep(&self, duration: Duration) {
+        let nanos = duration_to_nanos(duration);
+        let _ = self.nanos.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |time| {
+            Some(time.saturating_add(nanos))
+        });
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn spin_until(&self, deadline: i64) {
+        self.nanos.fetch_max(deadline, Ordering::SeqCst);
+    }
+}
+
+/// The time source used by `Stopwatch::new`.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub type DefaultClock = PerformanceClock;
+
+/// The time source used by `Stopwatch::new`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub type DefaultClock = MonotonicClock;
+
+pub(crate) fn secs_to_nanos(secs: f64) -> i64 {
+    (secs * 1e9).round() as i64
+}
+
+pub(crate) fn nanos_to_secs(nanos: i64) -> f64 {
+    nanos as f64 / 1e9
+}
+
+/// Converts a `Duration` to nanoseconds, saturating at `i64::MAX`.
+pub(crate) fn duration_to_nanos(duration: Duration) -> i64 {
+    i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock() {
+        let clock = ManualClock::new();
+        let other = clock.clone();
+        clock.advance(1.5);
+        assert_eq!(other.now(), 1_500_000_000);
+        other.set_time(4.0);
+        assert_eq!(clock.time(), 4.0);
+    }
+
+    #[test]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn manual_sleep() {
+        let clock = ManualClock::new();
+        clock.sleep(Duration::from_millis(500));
+        assert_eq!(clock.time(), 0.5);
+        clock.sleep(Duration::MAX);
+        assert_eq!(clock.now(), i64::MAX);
+    }
+
+    #[test]
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn monotonic_clock() {
+        let clock = MonotonicClock;
+        let start = clock.now();
+        assert!(start >= 0);
+        assert!(clock.now() >= start);
+    }
+}
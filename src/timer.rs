@@ -0,0 +1,118 @@
+//! Wakes tasks after a delay, for the async parts of the crate. Runtime-agnostic: it only needs a
+//! `Waker`.
+
+use std::task::Waker;
+use std::time::Duration;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use native::wake_after;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use web::wake_after;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod native {
+    use std::cmp::{Ordering, Reverse};
+    use std::collections::BinaryHeap;
+    use std::sync::{Condvar, Mutex, OnceLock};
+    use std::time::Instant;
+
+    use super::*;
+
+    struct Timer {
+        deadline: Instant,
+        waker: Waker,
+    }
+
+    impl PartialEq for Timer {
+        fn eq(&self, other: &Self) -> bool {
+            self.deadline == other.deadline
+        }
+    }
+
+    impl Eq for Timer {}
+
+    impl PartialOrd for Timer {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Timer {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.deadline.cmp(&other.deadline)
+        }
+    }
+
+    #[derive(Default)]
+    struct TimerQueue {
+        timers: Mutex<BinaryHeap<Reverse<Timer>>>,
+        changed: Condvar,
+    }
+
+    /// Wakes `waker` once `delay` has passed, using a single background thread shared by all
+    /// timers.
+    pub(crate) fn wake_after(delay: Duration, waker: Waker) {
+        static QUEUE: OnceLock<&'static TimerQueue> = OnceLock::new();
+        let queue = *QUEUE.get_or_init(|| {
+            let queue: &'static TimerQueue = Box::leak(Box::default());
+            std::thread::Builder::new()
+                .name("wasm-stopwatch-timer".to_string())
+                .spawn(move || run(queue))
+                .expect("failed to spawn the timer thread");
+            queue
+        });
+        let deadline = Instant::now() + delay;
+        queue.timers.lock().unwrap().push(Reverse(Timer { deadline, waker }));
+        queue.changed.notify_one();
+    }
+
+    fn run(queue: &TimerQueue) {
+        loop {
+            let mut timers = queue.timers.lock().unwrap();
+            let now = Instant::now();
+            let mut due = vec![];
+            while timers.peek().is_some_and(|timer| timer.0.deadline <= now) {
+                due.push(timers.pop().unwrap().0.waker);
+            }
+            if due.is_empty() {
+                let _timers = match timers.peek() {
+                    None => queue.changed.wait(timers).unwrap(),
+                    Some(timer) => {
+                        let timeout = timer.0.deadline - now;
+                        queue.changed.wait_timeout(timers, timeout).unwrap().0
+                    }
+                };
+            } else {
+                // Wake outside the lock, in case a waker polls its task inline and schedules
+                // another timer.
+                drop(timers);
+                for waker in due {
+                    waker.wake();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod web {
+    use js_sys::Function;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    use super::*;
+    use crate::js::*;
+
+    /// Wakes `waker` once `delay` has passed, using the global `setTimeout`, which is available in
+    /// windows, Web Workers and Node.js.
+    pub(crate) fn wake_after(delay: Duration, waker: Waker) {
+        let callback = Closure::once_into_js(move || waker.wake());
+        let millis = (delay.as_secs_f64() * 1000.0).ceil().min(i32::MAX as f64);
+        let global = js_sys::global();
+        let set_timeout: Function =
+            property(&global, "setTimeout").expect("setTimeout is unavailable").unchecked_into();
+        set_timeout
+            .call2(&global, &callback, &JsValue::from_f64(millis))
+            .expect("setTimeout failed");
+    }
+}
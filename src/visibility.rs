@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+use crate::shared::*;
+use crate::time_source::*;
+use crate::*;
+
+type Listener = Closure<dyn FnMut()>;
+
+/// Automatically pauses a `SharedStopwatch` while the page is hidden, so that returning to a
+/// background tab doesn't make the stopwatch's time leap forward.
+///
+/// Optionally, it can also pause the stopwatch while the window doesn't have focus. It only
+/// unpauses the stopwatch if it was the one that paused it, so pausing the stopwatch manually
+/// isn't overridden. When this is dropped, its listeners are removed and the stopwatch is unpaused
+/// if this had paused it.
+pub struct AutoPause<S: TimeSource + 'static = DefaultClock> {
+    state: Rc<RefCell<State<S>>>,
+    window: Window,
+    document: Document,
+    visibility_listener: Listener,
+    focus_listeners: Option<(Listener, Listener)>,
+}
+
+struct State<S: TimeSource> {
+    stopwatch: SharedStopwatch<S>,
+    pause_on_blur: bool,
+    blurred: bool,
+    paused_by_us: bool,
+    on_change: Option<Box<dyn FnMut(bool)>>,
+}
+
+impl<S: TimeSource + 'static> AutoPause<S> {
+    /// Starts pausing `stopwatch` while the page is hidden. If the page is already hidden, the
+    /// stopwatch is paused immediately.
+    ///
+    /// Returns an error if there's no document, for instance in a Web Worker.
+    pub fn new(stopwatch: SharedStopwatch<S>) -> Result<Self, StopwatchError> {
+        let window = window().ok_or(StopwatchError::NoDocument)?;
+        let document = window.document().ok_or(StopwatchError::NoDocument)?;
+        let state = Rc::new(RefCell::new(State {
+            stopwatch,
+            pause_on_blur: false,
+            blurred: false,
+            paused_by_us: false,
+            on_change: None,
+        }));
+
+        let visibility_listener = {
+            let state = Rc::downgrade(&state);
+            let document = document.clone();
+            Closure::wrap(Box::new(move || {
+                if let Some(state) = state.upgrade() {
+                    update(&state, document.hidden());
+                }
+            }) as Box<dyn FnMut()>)
+        };
+        add_listener(&document, "visibilitychange", &visibility_listener);
+        update(&state, document.hidden());
+
+        Ok(Self { state, window, document, visibility_listener, focus_listeners: None })
+    }
+
+    /// Sets whether the stopwatch is also paused while the window doesn't have focus. Defaults to
+    /// `false`.
+    pub fn set_pause_on_blur(&mut self, pause_on_blur: bool) {
+        self.state.borrow_mut().pause_on_blur = pause_on_blur;
+        if pause_on_blur && self.focus_listeners.is_none() {
+            let blur_listener = self.focus_listener(true);
+            let focus_listener = self.focus_listener(false);
+            add_listener(&self.window, "blur", &blur_listener);
+            add_listener(&self.window, "focus", &focus_listener);
+            self.focus_listeners = Some((blur_listener, focus_listener));
+            self.state.borrow_mut().blurred = !self.document.has_focus().unwrap_or(true);
+        } else if !pause_on_blur {
+            if let Some((blur_listener, focus_listener)) = self.focus_listeners.take() {
+                remove_listener(&self.window, "blur", &blur_listener);
+                remove_listener(&self.window, "focus", &focus_listener);
+            }
+            self.state.borrow_mut().blurred = false;
+        }
+        update(&self.state, self.document.hidden());
+    }
+
+    /// Calls `on_change` whenever this pauses or unpauses the stopwatch, with `true` if it was
+    /// paused and `false` if it was unpaused. Replaces any previous hook.
+    pub fn on_change(&mut self, on_change: impl FnMut(bool) + 'static) {
+        self.state.borrow_mut().on_change = Some(Box::new(on_change));
+    }
+
+    fn focus_listener(&self, blurred: bool) -> Listener {
+        let state = Rc::downgrade(&self.state);
+        let document = self.document.clone();
+        Closure::wrap(Box::new(move || {
+            if let Some(state) = state.upgrade() {
+                state.borrow_mut().blurred = blurred;
+                update(&state, document.hidden());
+            }
+        }) as Box<dyn FnMut()>)
+    }
+}
+
+impl<S: TimeSource + 'static> Drop for AutoPause<S> {
+    fn drop(&mut self) {
+        remove_listener(&self.document, "visibilitychange", &self.visibility_listener);
+        if let Some((blur_listener, focus_listener)) = &self.focus_listeners {
+            remove_listener(&self.window, "blur", blur_listener);
+            remove_listener(&self.window, "focus", focus_listener);
+        }
+        let state = self.state.borrow();
+        if state.paused_by_us {
+            state.stopwatch.unpause();
+        }
+    }
+}
+
+/// Pauses or unpauses the stopwatch to match the page's state, and calls the hook if anything
+/// changed.
+fn update<S: TimeSource>(state: &RefCell<State<S>>, hidden: bool) {
+    let mut on_change = {
+        let mut state = state.borrow_mut();
+        let should_pause = hidden || (state.pause_on_blur && state.blurred);
+        if should_pause && !state.paused_by_us && !state.stopwatch.paused() {
+            state.stopwatch.pause();
+            state.paused_by_us = true;
+        } else if !should_pause && state.paused_by_us {
+            state.stopwatch.unpause();
+            state.paused_by_us = false;
+        } else {
+            return;
+        }
+        state.on_change.take()
+    };
+
+    // The hook runs without the state borrowed, so it can use the `AutoPause`.
+    let paused = state.borrow().paused_by_us;
+    if let Some(on_change) = &mut on_change {
+        on_change(paused);
+    }
+    let mut state = state.borrow_mut();
+    if state.on_change.is_none() {
+        state.on_change = on_change;
+    }
+}
+
+fn add_listener(target: &EventTarget, event: &str, listener: &Listener) {
+    target.add_event_listener_with_callback(event, listener.as_ref().unchecked_ref()).unwrap();
+}
+
+fn remove_listener(target: &EventTarget, event: &str, listener: &Listener) {
+    let _ = target.remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+}